@@ -8,5 +8,7 @@
 LICENSE END */
 
 fn main() {
+    // Only the app has a window, the command line tool is built without sixtyfps
+    #[cfg(feature = "gui")]
     sixtyfps_build::compile("str8ts-puzzle.60").unwrap();
 }
@@ -0,0 +1,777 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use crate::translations::explain_violation;
+use crate::settings::{settings_path, Settings};
+use str8ts_core::themes::{builtin_theme, BUILTIN_THEMES};
+use str8ts_core::{Board, Constraints, Digit, Layout, RuleSet, DEFAULT_SIZE, MAX_SIZE, MIN_SIZE};
+use str8ts_core::collection::{format_entry, Collection, CollectionEntry};
+use str8ts_core::formats::{format_puzzle, parse_puzzle, parse_savegame, puzzle_id, Format};
+use str8ts_core::generator::generate_puzzle;
+use str8ts_core::image_import::import_image;
+use str8ts_core::print::{render_pdf, render_svg, PrintPuzzle, PUZZLES_PER_PAGE};
+use str8ts_core::thumbnail::{encode_png, render_thumbnail};
+use str8ts_core::solver::{analyze_puzzle, search_solutions, solve_backtrack,
+    Difficulty, Str8tsSolution};
+use crate::translations::translation;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// Exit codes
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1; // invalid puzzle, no solution, nothing generated
+const EXIT_USAGE: i32 = 2;
+const EXIT_IO: i32 = 3; // file can't be read, parsed or written
+const EXIT_MULTIPLE_SOLUTIONS: i32 = 4;
+
+const USAGE: &str = "Usage: str8ts <command> [options]
+
+Commands:
+  solve <file>             print the solution of the puzzle
+  count <file> [--max N]   print the number of solutions (counted up to N, default 1000)
+  grade <file>             print the estimated difficulty of the puzzle
+  validate <file>          print the rules the board breaks
+  generate [--difficulty easy|medium|hard|extreme] [--seed N] [--count N] [--black PERCENT]
+           [--size 4-12] [--rules classic|sudoku] [--layout samurai] [--timeout SECONDS]
+                           print newly generated puzzles (default size 9, classic rules,
+                           a single grid; samurai: five overlapping 9×9 grids); gives up
+                           after the timeout (default 60 seconds for all puzzles). Hard and
+                           extreme puzzles and samurai puzzles are rarely found
+  convert <input> <output> convert a puzzle between formats
+  import <image> [--size 4-12]
+                           read a puzzle from a PNG/JPEG image of a grid (size detected
+                           unless given); check the result, digits may be misread
+  print <files...> [--per-page 1|2|4|6] [--title TEXT] [--show progress,pencil,solution]
+                           print puzzles on A4 pages as PDF or SVG (one file per page:
+                           name-1.svg, ...), with a header of title, difficulty and ID;
+                           --show adds the player's numbers and small numbers of
+                           savegames, and pages with the solutions
+  thumbnail <file> [--size PIXELS] [--theme NAME]
+                           draw the board as a square PNG (default 256 pixels) in the
+                           colors of a built-in theme (default: the theme of the settings)
+  collection add <collection> <files...> [--title TEXT] [--author TEXT] [--source TEXT]
+           [--difficulty NAME]
+                           append puzzles to a collection file (created if missing), with
+                           their difficulty and solution if unique; puzzles the collection
+                           has already, also rotated, reflected or with inverted numbers,
+                           are skipped
+  collection list <collection>
+                           print the ID, title, author, difficulty and source of each puzzle
+  collection dedup <collection>
+                           remove puzzles the collection has already
+  collection get <collection> <ID|number>
+                           write a puzzle of the collection, e.g. as a savegame to load
+
+Options:
+  --from json|txt          format of the input (default: from the file extension, txt for -)
+  --to json|txt            format of the output (default: from the file extension, txt for -;
+                           print: pdf|svg, default pdf)
+  --output <file>          where solve, generate, import, print, thumbnail and collection get
+                           write their result (default: -)
+
+Files can be - for standard input/output. In the txt format, each line is a row of the board:
+'.' is an empty white cell, '1'-'9' and 'A'-'C' a white cell with the number 1-12, '#' an empty
+black cell and 'a'-'l' a black cell with the number 1-12. The number of lines is the board size.
+A first line 'rules: sudoku' marks a Sudoku-Str8ts puzzle, where no number repeats in a box.
+Lines like 'clue: left 3 sum 17' add outside clues: the sum of the first straight, or the
+lowest/highest number (min/max) of a row/column, seen from the top, bottom, left or right.
+A line 'layout: samurai' marks a puzzle of five overlapping 9×9 grids. Its lines cover all
+grids (21 lines of 21 characters), cells outside the grids are '#'.
+Collection files hold a puzzle per line as a JSON object with the fields id, title, author,
+difficulty, source, puzzle and solution (the last two in the txt format).
+
+Exit codes: 0 success, 1 invalid/unsolvable puzzle or nothing generated, 2 usage error,
+3 file error, 4 multiple solutions (solve, grade)";
+
+// Attempts of the generator per puzzle of the requested difficulty
+const GENERATOR_ATTEMPTS: usize = 100;
+// Time in seconds the generate command may take for all puzzles
+const DEFAULT_GENERATOR_TIMEOUT: u64 = 60;
+
+enum CliError {
+    Usage(String),
+    Io(String)
+}
+
+// Arguments after the command: positional arguments and --name value options
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Args, CliError> {
+        let mut positional = vec![];
+        let mut options = HashMap::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if let Some(name) = arg.strip_prefix("--") {
+                let value = args.next()
+                    .ok_or_else(|| CliError::Usage(format!("Missing value for --{}.", name)))?;
+                options.insert(name.to_string(), value.clone());
+            } else {
+                positional.push(arg.clone());
+            }
+        }
+        Ok(Args { positional, options })
+    }
+
+    // The single input file of the solve, count, grade and validate commands
+    fn input(&self) -> Result<&str, CliError> {
+        match &self.positional[..] {
+            [file] => Ok(file),
+            _ => Err(CliError::Usage("Expected exactly one file.".into()))
+        }
+    }
+
+    fn format(&self, name: &str, path: &str) -> Result<Format, CliError> {
+        match self.options.get(name) {
+            Some(format) => Format::from_name(format)
+                .ok_or_else(|| CliError::Usage(format!("Unknown format {}.", format))),
+            None if path == "-" => Ok(Format::Text),
+            None => Ok(Format::from_path(Path::new(path)))
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, CliError> {
+        match self.options.get(name) {
+            Some(value) => value.parse()
+                .map_err(|_| CliError::Usage(format!("Invalid number for --{}: {}.", name, value))),
+            None => Ok(default)
+        }
+    }
+}
+
+// Run a command, returns the exit code
+pub fn run(args: &[String]) -> i32 {
+    let (command, args) = match args.split_first() {
+        Some(command_and_args) => command_and_args,
+        None => {
+            eprintln!("No command given.\n\n{}", USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    if command == "help" || command == "--help" || command == "-h" {
+        println!("{}", USAGE);
+        return EXIT_OK;
+    }
+
+    let result = Args::parse(args).and_then(|args| match command.as_str() {
+        "solve" => solve(&args),
+        "count" => count(&args),
+        "grade" => grade(&args),
+        "validate" => validate(&args),
+        "generate" => generate(&args),
+        "convert" => convert(&args),
+        "import" => import(&args),
+        "print" => print(&args),
+        "thumbnail" => thumbnail(&args),
+        "collection" => collection(&args),
+        _ => Err(CliError::Usage(format!("Unknown command {}.", command)))
+    });
+    match result {
+        Ok(exit_code) => exit_code,
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            EXIT_USAGE
+        },
+        Err(CliError::Io(message)) => {
+            eprintln!("{}", message);
+            EXIT_IO
+        }
+    }
+}
+
+fn read_puzzle(path: &str, format: Format) -> Result<Board, CliError> {
+    parse_puzzle(&read_file(path)?, format)
+        .map_err(|error| CliError::Io(format!("Unable to parse {}: {}", path, error)))
+}
+
+fn read_file(path: &str) -> Result<String, CliError> {
+    let mut data = String::new();
+    if path == "-" {
+        std::io::stdin().read_to_string(&mut data)
+            .map_err(|error| CliError::Io(format!("Unable to read standard input: {}", error)))?;
+    } else {
+        data = std::fs::read_to_string(path)
+            .map_err(|error| CliError::Io(format!("Unable to read file {}: {}", path, error)))?;
+    }
+    Ok(data)
+}
+
+fn write_output(path: &str, data: impl AsRef<[u8]>) -> Result<(), CliError> {
+    if path == "-" {
+        std::io::stdout().write_all(data.as_ref())
+    } else {
+        std::fs::write(path, data)
+    }.map_err(|error| CliError::Io(format!("Unable to write {}: {}", path, error)))
+}
+
+// Read the input file and keep only the puzzle: black cells and fixed numbers
+fn read_input_puzzle(args: &Args) -> Result<Board, CliError> {
+    let path = args.input()?;
+    Ok(read_puzzle(path, args.format("from", path)?)?.puzzle())
+}
+
+// Print the broken rules of a board, returns whether it is valid
+fn check_rules(board: &Board) -> bool {
+    let constraints = Constraints::new(board);
+    let violations = constraints.validate(board);
+    for (k, violation) in &violations {
+        eprintln!("{}", explain_violation(translation("en"), &constraints.explain(*k, violation)));
+    }
+    violations.is_empty()
+}
+
+fn difficulty_name(difficulty: Difficulty) -> &'static str {
+    match difficulty {
+        Difficulty::Easy => "easy",
+        Difficulty::Medium => "medium",
+        Difficulty::Hard => "hard",
+        Difficulty::Extreme => "extreme"
+    }
+}
+
+fn solve(args: &Args) -> Result<i32, CliError> {
+    let puzzle = read_input_puzzle(args)?;
+    if !check_rules(&puzzle) {
+        return Ok(EXIT_FAILURE);
+    }
+    let (solution, exit_code) = match solve_backtrack(puzzle) {
+        Str8tsSolution::None => {
+            eprintln!("The puzzle has no solution.");
+            return Ok(EXIT_FAILURE);
+        },
+        Str8tsSolution::Unique(solution) => (solution, EXIT_OK),
+        Str8tsSolution::Multiple(solution) => {
+            eprintln!("The puzzle has more than one solution, printing one of them.");
+            (solution, EXIT_MULTIPLE_SOLUTIONS)
+        }
+    };
+    let output = args.options.get("output").map(|output| output.as_str()).unwrap_or("-");
+    write_output(output, format_puzzle(&solution, args.format("to", output)?))?;
+    Ok(exit_code)
+}
+
+fn count(args: &Args) -> Result<i32, CliError> {
+    let puzzle = read_input_puzzle(args)?;
+    if !check_rules(&puzzle) {
+        return Ok(EXIT_FAILURE);
+    }
+    let result = search_solutions(puzzle, args.number("max", 1000)?, &|_| false);
+    println!("{}{}", result.solutions.len(), if result.is_exhaustive { "" } else { "+" });
+    Ok(EXIT_OK)
+}
+
+fn grade(args: &Args) -> Result<i32, CliError> {
+    let puzzle = read_input_puzzle(args)?;
+    if !check_rules(&puzzle) {
+        return Ok(EXIT_FAILURE);
+    }
+    let analysis = analyze_puzzle(puzzle, 2, &|_| false).expect("The analysis is not cancelled.");
+    match analysis.difficulty {
+        Some(difficulty) => {
+            println!("{}", difficulty_name(difficulty));
+            Ok(EXIT_OK)
+        },
+        None if analysis.solution_count == 0 => {
+            eprintln!("The puzzle has no solution.");
+            Ok(EXIT_FAILURE)
+        },
+        None => {
+            eprintln!("The puzzle has more than one solution.");
+            Ok(EXIT_MULTIPLE_SOLUTIONS)
+        }
+    }
+}
+
+fn validate(args: &Args) -> Result<i32, CliError> {
+    let path = args.input()?;
+    let board = read_puzzle(path, args.format("from", path)?)?;
+    if check_rules(&board) {
+        println!("The board is valid.");
+        Ok(EXIT_OK)
+    } else {
+        Ok(EXIT_FAILURE)
+    }
+}
+
+fn generate(args: &Args) -> Result<i32, CliError> {
+    let difficulty = match args.options.get("difficulty").map(|name| name.as_str()) {
+        None => None,
+        Some("easy") => Some(Difficulty::Easy),
+        Some("medium") => Some(Difficulty::Medium),
+        Some("hard") => Some(Difficulty::Hard),
+        Some("extreme") => Some(Difficulty::Extreme),
+        Some(name) => return Err(CliError::Usage(format!("Unknown difficulty {}.", name)))
+    };
+    let seed = args.number("seed", rand::thread_rng().gen())?;
+    let count = args.number("count", 1)?;
+    let size = args.number("size", DEFAULT_SIZE)?;
+    if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        return Err(CliError::Usage(format!("The size must be between {} and {}.", MIN_SIZE, MAX_SIZE)));
+    }
+    let layout = match args.options.get("layout") {
+        Some(name) => Layout::from_name(name).ok_or_else(|| CliError::Usage(format!("Unknown layout {}.", name)))?,
+        None => Layout::single(size)
+    };
+    let rules = match args.options.get("rules") {
+        Some(name) => RuleSet::from_name(name).ok_or_else(|| CliError::Usage(format!("Unknown rules {}.", name)))?,
+        None => RuleSet::classic()
+    };
+    let options = Settings::default().generator;
+    let p_white = match args.options.get("black") {
+        Some(_) => 1.0 - args.number::<f64>("black", 0.0)? / 100.0,
+        None => options.p_white
+    };
+    let timeout = args.number("timeout", DEFAULT_GENERATOR_TIMEOUT)?;
+    let output = args.options.get("output").map(|output| output.as_str()).unwrap_or("-");
+    let format = args.format("to", output)?;
+
+    // The generator and the analysis stop once the deadline has passed
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    let is_cancelled = |_: usize| Instant::now() >= deadline;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut puzzles = vec![];
+    while puzzles.len() < count {
+        // Searches only check the deadline every few hundred steps, so check it between attempts too
+        let puzzle = (0..GENERATOR_ATTEMPTS)
+            .take_while(|_| !is_cancelled(0))
+            .filter_map(|_| generate_puzzle(&mut rng, &layout, rules, options.p_fixed, p_white, &is_cancelled))
+            .find(|puzzle| match analyze_puzzle(puzzle.clone(), 2, &is_cancelled) {
+                Some(analysis) => analysis.difficulty.is_some()
+                    && (difficulty.is_none() || analysis.difficulty == difficulty),
+                None => false
+            });
+        match puzzle {
+            Some(puzzle) => puzzles.push(format_puzzle(&puzzle, format)),
+            None if is_cancelled(0) => {
+                eprintln!("Unable to generate the puzzles within {} seconds (seed {}).", timeout, seed);
+                return Ok(EXIT_FAILURE);
+            },
+            None => {
+                eprintln!("Unable to generate a puzzle (seed {}).", seed);
+                return Ok(EXIT_FAILURE);
+            }
+        }
+    }
+    // Text puzzles are separated by empty lines, JSON puzzles are written one per line
+    let mut data = puzzles.join("\n");
+    if format == Format::Json {
+        data.push('\n');
+    }
+    write_output(output, &data)?;
+    Ok(EXIT_OK)
+}
+
+fn convert(args: &Args) -> Result<i32, CliError> {
+    let (input, output) = match &args.positional[..] {
+        [input, output] => (input, output),
+        _ => return Err(CliError::Usage("Expected an input and an output file.".into()))
+    };
+    let board = read_puzzle(input, args.format("from", input)?)?;
+    write_output(output, format_puzzle(&board, args.format("to", output)?))?;
+    Ok(EXIT_OK)
+}
+
+fn import(args: &Args) -> Result<i32, CliError> {
+    let path = args.input()?;
+    let size = match args.options.get("size") {
+        Some(_) => Some(args.number("size", DEFAULT_SIZE)?),
+        None => None
+    };
+    let board = import_image(Path::new(path), size)
+        .map_err(|error| CliError::Io(format!("Unable to import {}: {}", path, error)))?;
+    let output = args.options.get("output").map(|output| output.as_str()).unwrap_or("-");
+    write_output(output, format_puzzle(&board, args.format("to", output)?))?;
+    Ok(EXIT_OK)
+}
+
+// What the print command shows besides the puzzle
+struct PrintContent {
+    progress: bool, // numbers entered by the player
+    pencil_marks: bool, // small numbers of savegames
+    solutions: bool,
+}
+
+fn print(args: &Args) -> Result<i32, CliError> {
+    if args.positional.is_empty() {
+        return Err(CliError::Usage("Expected at least one file.".into()));
+    }
+    let per_page = args.number("per-page", 1)?;
+    if !PUZZLES_PER_PAGE.contains(&per_page) {
+        return Err(CliError::Usage(format!("Unsupported number of puzzles per page: {}.", per_page)));
+    }
+    let mut content = PrintContent { progress: false, pencil_marks: false, solutions: false };
+    let show = args.options.get("show").map(|show| show.as_str()).unwrap_or("");
+    for name in show.split(',').filter(|name| !name.is_empty()) {
+        match name {
+            "progress" => content.progress = true,
+            "pencil" => content.pencil_marks = true,
+            "solution" => content.solutions = true,
+            _ => return Err(CliError::Usage(format!("Unknown --show value {}.", name)))
+        }
+    }
+    let output = args.options.get("output").map(|output| output.as_str()).unwrap_or("-");
+    let is_svg = match args.options.get("to").map(|to| to.as_str()) {
+        Some("svg") => true,
+        Some("pdf") => false,
+        Some(to) => return Err(CliError::Usage(format!("Unknown format {}.", to))),
+        None => output.ends_with(".svg")
+    };
+
+    let mut puzzles = vec![];
+    for (k, path) in args.positional.iter().enumerate() {
+        // Without a title, puzzles are named after their file
+        let title = match args.options.get("title") {
+            Some(title) if args.positional.len() > 1 => format!("{} {}", title, k + 1),
+            Some(title) => title.clone(),
+            None => Path::new(path).file_stem().map_or(path.clone(), |stem| stem.to_string_lossy().into())
+        };
+        puzzles.push(print_puzzle(path, args.format("from", path)?, title, &content)?);
+    }
+
+    if is_svg {
+        let pages = render_svg(&puzzles, per_page).map_err(|error| CliError::Io(format!("Unable to print: {}", error)))?;
+        if pages.len() == 1 {
+            write_output(output, &pages[0])?;
+        } else if output == "-" {
+            return Err(CliError::Usage("SVG output of several pages needs an output file.".into()));
+        } else {
+            let stem = output.strip_suffix(".svg").unwrap_or(output);
+            for (k, page) in pages.iter().enumerate() {
+                write_output(&format!("{}-{}.svg", stem, k + 1), page)?;
+            }
+        }
+    } else {
+        let pdf = render_pdf(&puzzles, per_page).map_err(|error| CliError::Io(format!("Unable to print: {}", error)))?;
+        write_output(output, pdf)?;
+    }
+    Ok(EXIT_OK)
+}
+
+// Read a puzzle to print. Its difficulty and solution are only known for puzzles with a
+// unique solution.
+fn print_puzzle(path: &str, format: Format, title: String, content: &PrintContent) -> Result<PrintPuzzle, CliError> {
+    let data = read_file(path)?;
+    let board = parse_puzzle(&data, format)
+        .map_err(|error| CliError::Io(format!("Unable to parse {}: {}", path, error)))?;
+    let puzzle = board.puzzle();
+    // Only savegames have small numbers
+    let pencil_marks = match format {
+        Format::Json if content.pencil_marks => parse_savegame(&data)
+            .map_err(|error| CliError::Io(format!("Unable to parse {}: {}", path, error)))?
+            .cells.iter()
+            .map(|data| (1..=data.3.len()).filter(|&k| data.3[k - 1]).filter_map(|k| Digit::new(k as i32)).collect())
+            .collect(),
+        _ => vec![]
+    };
+    let analysis = analyze_puzzle(puzzle.clone(), 2, &|_| false).expect("The analysis is not cancelled.");
+    let solution = if content.solutions {
+        match solve_backtrack(puzzle.clone()) {
+            Str8tsSolution::Unique(solution) => Some(solution),
+            _ => None
+        }
+    } else {
+        None
+    };
+    Ok(PrintPuzzle {
+        id: puzzle_id(&puzzle),
+        board: if content.progress { board } else { puzzle },
+        pencil_marks,
+        title,
+        difficulty: analysis.difficulty.map(|difficulty| difficulty_name(difficulty).to_string()),
+        solution,
+    })
+}
+
+// Largest thumbnail, in pixels per side
+const MAX_THUMBNAIL_SIZE: u32 = 4096;
+
+fn thumbnail(args: &Args) -> Result<i32, CliError> {
+    let path = args.input()?;
+    let board = read_puzzle(path, args.format("from", path)?)?;
+    let size = args.number("size", 256)?;
+    if size == 0 || size > MAX_THUMBNAIL_SIZE {
+        return Err(CliError::Usage(format!("Unsupported thumbnail size {}.", size)));
+    }
+    // Themes of the settings that aren't built in (user themes) fall back to the first built-in theme
+    let theme = match args.options.get("theme") {
+        Some(theme) => builtin_theme(theme).ok_or_else(|| {
+            let names = BUILTIN_THEMES.iter().map(|theme| theme.name).collect::<Vec<_>>();
+            CliError::Usage(format!("Unknown theme {}, expected one of: {}.", theme, names.join(", ")))
+        })?,
+        None => builtin_theme(&Settings::load(&settings_path()).theme).unwrap_or(&BUILTIN_THEMES[0])
+    };
+    let colors = theme.board_colors();
+    let png = encode_png(&render_thumbnail(&board, size, &colors))
+        .map_err(|error| CliError::Io(format!("Unable to draw {}: {}", path, error)))?;
+    let output = args.options.get("output").map(|output| output.as_str()).unwrap_or("-");
+    write_output(output, png)?;
+    Ok(EXIT_OK)
+}
+
+fn collection(args: &Args) -> Result<i32, CliError> {
+    let (action, path, rest) = match &args.positional[..] {
+        [action, path, rest @ ..] => (action.as_str(), path.as_str(), rest),
+        _ => return Err(CliError::Usage("Expected an action and a collection file.".into()))
+    };
+    if path == "-" {
+        return Err(CliError::Usage("The collection must be a file.".into()));
+    }
+    match (action, rest) {
+        ("add", [_, ..]) => add_to_collection(args, path, rest),
+        ("list", []) => {
+            for entry in read_collection(path)?.entries() {
+                println!("{}\t{}\t{}\t{}\t{}", entry.id, entry.title, entry.author, entry.difficulty, entry.source);
+            }
+            Ok(EXIT_OK)
+        },
+        ("dedup", []) => {
+            let mut collection = read_collection(path)?;
+            let duplicates = collection.remove_duplicates();
+            if !duplicates.is_empty() {
+                write_output(path, collection.to_text())?;
+            }
+            println!("Removed {} duplicate puzzles.", duplicates.len());
+            Ok(EXIT_OK)
+        },
+        // Puzzles are found by their ID, or by their number in the collection (from 1)
+        ("get", [key]) => {
+            let collection = read_collection(path)?;
+            let entries = collection.entries();
+            let entry = entries.iter().find(|entry| &entry.id == key)
+                .or_else(|| key.parse::<usize>().ok().and_then(|k| k.checked_sub(1)).and_then(|k| entries.get(k)))
+                .ok_or_else(|| CliError::Usage(format!("No puzzle {} in {}.", key, path)))?;
+            let board = entry.board().map_err(|error| CliError::Io(format!("Unable to parse {}: {}", path, error)))?;
+            let output = args.options.get("output").map(|output| output.as_str()).unwrap_or("-");
+            write_output(output, format_puzzle(&board, args.format("to", output)?))?;
+            Ok(EXIT_OK)
+        },
+        ("add", _) => Err(CliError::Usage("Expected at least one puzzle file.".into())),
+        ("list", _) | ("dedup", _) => Err(CliError::Usage(format!("Unexpected arguments for collection {}.", action))),
+        ("get", _) => Err(CliError::Usage("Expected a puzzle ID or number.".into())),
+        _ => Err(CliError::Usage(format!("Unknown collection action {}.", action)))
+    }
+}
+
+fn read_collection(path: &str) -> Result<Collection, CliError> {
+    Collection::parse(&read_file(path)?).map_err(|error| CliError::Io(format!("Unable to parse {}: {}", path, error)))
+}
+
+// Append the new puzzles to the end of the file, without rewriting the entries it has
+fn add_to_collection(args: &Args, path: &str, files: &[String]) -> Result<i32, CliError> {
+    let mut collection = if Path::new(path).exists() { read_collection(path)? } else { Collection::new() };
+    let mut data = String::new();
+    for (k, file) in files.iter().enumerate() {
+        let puzzle = read_puzzle(file, args.format("from", file)?)?.puzzle();
+        if collection.contains(&puzzle) {
+            eprintln!("Skipping {}: the collection has this puzzle already.", file);
+            continue;
+        }
+        // Without a title, puzzles are named after their file
+        let title = match args.options.get("title") {
+            Some(title) if files.len() > 1 => format!("{} {}", title, k + 1),
+            Some(title) => title.clone(),
+            None => Path::new(file).file_stem().map_or(file.clone(), |stem| stem.to_string_lossy().into())
+        };
+        let mut entry = CollectionEntry {
+            title,
+            author: args.options.get("author").cloned().unwrap_or_default(),
+            difficulty: args.options.get("difficulty").cloned().unwrap_or_default(),
+            source: args.options.get("source").cloned().unwrap_or_default(),
+            ..CollectionEntry::new(&puzzle)
+        };
+        // The difficulty and solution are only known for puzzles with a unique solution
+        match solve_backtrack(puzzle.clone()) {
+            Str8tsSolution::Unique(solution) => {
+                entry = entry.with_solution(&solution);
+                if entry.difficulty.is_empty() {
+                    let analysis = analyze_puzzle(puzzle, 2, &|_| false).expect("The analysis is not cancelled.");
+                    entry.difficulty = analysis.difficulty.map_or(String::new(), |difficulty| difficulty_name(difficulty).to_string());
+                }
+            },
+            _ => eprintln!("{} has no unique solution.", file)
+        }
+        data.push_str(&format_entry(&entry));
+        collection.append(entry).map_err(|error| CliError::Io(format!("Unable to parse {}: {}", file, error)))?;
+    }
+    std::fs::OpenOptions::new().create(true).append(true).open(path)
+        .and_then(|mut file| file.write_all(data.as_bytes()))
+        .map_err(|error| CliError::Io(format!("Unable to write {}: {}", path, error)))?;
+    Ok(EXIT_OK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const PUZZLE: &str = "c...a.\n.2.##.\n#5.##.\n#..#3.\n...##6\n.6#1.#\n";
+    const SOLUTION: &str = "c465a2\n123##5\n#54##3\n#12#34\n435##6\n56#12#\n";
+
+    // A directory of its own for each test, removed at the end of the test
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> TempDir {
+            let path = std::env::temp_dir().join(format!("str8ts-test-{}-{}", std::process::id(), name));
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn file(&self, name: &str, data: &str) -> String {
+            let path = self.path(name);
+            std::fs::write(&path, data).unwrap();
+            path
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_str().unwrap().to_string()
+        }
+
+        fn read(&self, name: &str) -> String {
+            std::fs::read_to_string(self.path(name)).unwrap()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn run_command(args: &[&str]) -> i32 {
+        run(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn solve() {
+        let dir = TempDir::new("solve");
+        let puzzle = dir.file("puzzle.txt", PUZZLE);
+        let output = dir.path("solution.txt");
+        assert_eq!(run_command(&["solve", &puzzle, "--output", &output]), EXIT_OK);
+        assert_eq!(dir.read("solution.txt"), SOLUTION);
+
+        // The digits of the player in a savegame are ignored
+        let puzzle = parse_puzzle(PUZZLE, Format::Text).unwrap();
+        let mut game = parse_puzzle(SOLUTION, Format::Text).unwrap();
+        for i in 0..game.len() {
+            game[i].is_fixed = puzzle[i].is_fixed;
+        }
+        game[1].digit = Digit::new(4);
+        let savegame = dir.file("game.json", &format_puzzle(&game, Format::Json));
+        assert_eq!(run_command(&["solve", &savegame, "--output", &output, "--to", "txt"]), EXIT_OK);
+        assert_eq!(dir.read("solution.txt"), SOLUTION);
+
+        let multiple = dir.file("multiple.txt", "....\n....\n....\n....\n");
+        assert_eq!(run_command(&["solve", &multiple, "--output", &output]), EXIT_MULTIPLE_SOLUTIONS);
+        let unsolvable = dir.file("unsolvable.txt", "#1.#\n##2#\n####\n####\n");
+        assert_eq!(run_command(&["solve", &unsolvable, "--output", &output]), EXIT_FAILURE);
+        let invalid = dir.file("invalid.txt", "11..\n....\n....\n....\n");
+        assert_eq!(run_command(&["solve", &invalid, "--output", &output]), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn usage_and_file_errors() {
+        let dir = TempDir::new("errors");
+        assert_eq!(run_command(&[]), EXIT_USAGE);
+        assert_eq!(run_command(&["unknown"]), EXIT_USAGE);
+        assert_eq!(run_command(&["solve"]), EXIT_USAGE);
+        assert_eq!(run_command(&["solve", &dir.path("missing.txt")]), EXIT_IO);
+        let broken = dir.file("broken.txt", "...\n");
+        assert_eq!(run_command(&["solve", &broken]), EXIT_IO);
+        assert_eq!(run_command(&["solve", &broken, "--from"]), EXIT_USAGE);
+        assert_eq!(run_command(&["solve", &broken, "--from", "xml"]), EXIT_USAGE);
+    }
+
+    #[test]
+    fn validate() {
+        let dir = TempDir::new("validate");
+        assert_eq!(run_command(&["validate", &dir.file("puzzle.txt", PUZZLE)]), EXIT_OK);
+        assert_eq!(run_command(&["validate", &dir.file("solution.txt", SOLUTION)]), EXIT_OK);
+        let broken = SOLUTION.replacen('4', "6", 1);
+        assert_eq!(run_command(&["validate", &dir.file("broken.txt", &broken)]), EXIT_FAILURE);
+        let clue = format!("clue: left 1 sum 14\n{}", SOLUTION);
+        assert_eq!(run_command(&["validate", &dir.file("clue.txt", &clue)]), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn generate() {
+        let dir = TempDir::new("generate");
+        let output = dir.path("puzzles.txt");
+        let args = ["generate", "--seed", "1", "--size", "6", "--count", "2", "--output", &output];
+        assert_eq!(run_command(&args), EXIT_OK);
+        let puzzles = dir.read("puzzles.txt");
+        // The same seed gives the same puzzles
+        assert_eq!(run_command(&args), EXIT_OK);
+        assert_eq!(dir.read("puzzles.txt"), puzzles);
+
+        let puzzles = puzzles.split("\n\n").map(|text| parse_puzzle(text, Format::Text).unwrap()).collect::<Vec<_>>();
+        assert_eq!(puzzles.len(), 2);
+        for puzzle in puzzles {
+            assert_eq!(puzzle.size(), 6);
+            assert!(matches!(solve_backtrack(puzzle), Str8tsSolution::Unique(_)));
+        }
+
+        let output = dir.path("puzzles.json");
+        let args = ["generate", "--seed", "2", "--size", "4", "--rules", "sudoku", "--count", "3", "--output", &output];
+        assert_eq!(run_command(&args), EXIT_OK);
+        let puzzles = dir.read("puzzles.json");
+        assert_eq!(puzzles.lines().count(), 3);
+        assert!(puzzles.lines().all(|line| parse_puzzle(line, Format::Json).unwrap().rules() == RuleSet::sudoku()));
+
+        // The default 9×9 board is generated well within the timeout
+        let output = dir.path("default.txt");
+        assert_eq!(run_command(&["generate", "--seed", "3", "--timeout", "20", "--output", &output]), EXIT_OK);
+        assert_eq!(parse_puzzle(&dir.read("default.txt"), Format::Text).unwrap().size(), 9);
+    }
+
+    #[test]
+    fn generate_errors() {
+        let dir = TempDir::new("generate-errors");
+        let output = dir.path("puzzles.txt");
+        assert_eq!(run_command(&["generate", "--timeout", "0", "--output", &output]), EXIT_FAILURE);
+        assert_eq!(run_command(&["generate", "--difficulty", "impossible"]), EXIT_USAGE);
+        assert_eq!(run_command(&["generate", "--count", "some"]), EXIT_USAGE);
+        assert_eq!(run_command(&["generate", "--size", "13"]), EXIT_USAGE);
+        assert_eq!(run_command(&["generate", "--layout", "triangle"]), EXIT_USAGE);
+        assert_eq!(run_command(&["generate", "--timeout", "soon"]), EXIT_USAGE);
+    }
+
+    #[test]
+    fn convert() {
+        let dir = TempDir::new("convert");
+        let text = format!("rules: sudoku\nclue: right 2 min 1\n{}", PUZZLE);
+        let input = dir.file("puzzle.txt", &text);
+        let json = dir.path("puzzle.json");
+        let back = dir.path("back.txt");
+        assert_eq!(run_command(&["convert", &input, &json]), EXIT_OK);
+        assert!(dir.read("puzzle.json").contains("\"clues\""));
+        assert_eq!(run_command(&["convert", &json, &back]), EXIT_OK);
+        assert_eq!(dir.read("back.txt"), text);
+
+        // Samurai boards keep their layout
+        let samurai = format_puzzle(&Board::empty_with_layout(Layout::samurai()), Format::Text);
+        let input = dir.file("samurai.txt", &samurai);
+        assert_eq!(run_command(&["convert", &input, &json]), EXIT_OK);
+        assert_eq!(run_command(&["convert", &json, &back]), EXIT_OK);
+        assert_eq!(dir.read("back.txt"), samurai);
+        // The format can be given instead of the extension
+        let output = dir.path("samurai.out");
+        assert_eq!(run_command(&["convert", &input, &output, "--to", "json"]), EXIT_OK);
+        assert_eq!(dir.read("samurai.out"), dir.read("puzzle.json"));
+
+        assert_eq!(run_command(&["convert", &input]), EXIT_USAGE);
+    }
+}
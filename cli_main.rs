@@ -0,0 +1,23 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+// The command line tool (see cli.rs). It shares the settings and translations with the app,
+// but doesn't depend on sixtyfps.
+
+mod cli;
+// Only the theme and the English explanations of broken rules are used here
+#[allow(dead_code)]
+mod settings;
+#[allow(dead_code)]
+mod translations;
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    std::process::exit(cli::run(&args));
+}
@@ -7,42 +7,133 @@
     SPDX-License-Identifier: GPL-3.0-only
 LICENSE END */
 
-mod vec_or_vec_model;
-mod str8ts_row;
-mod str8ts_board;
+#![cfg(feature = "gui")]
 
+mod settings;
+mod user_themes;
+mod translations;
+mod worker;
+
+use sixtyfps::Color;
 use sixtyfps::Model;
 use sixtyfps::ModelHandle;
 use sixtyfps::VecModel;
 use sixtyfps::re_exports::KeyEvent;
 use std::cell::RefCell;
 use std::rc::Rc;
-use serde_json;
-use vec_or_vec_model::VecOrVecModel;
-use str8ts_row::Row;
-use str8ts_board::{solve_backtrack, generate_puzzle, compute_rows_columns,
-    empty_board, random_board, Str8tsSolution};
+use str8ts_core::{Board, BoardCell, CellKind, CellStore, Clue, ClueKind, Constraints, Digit, Layout, Row, RuleSet,
+    Violation};
+use str8ts_core::constraints::Unit;
+use str8ts_core::formats::{parse_savegame, cell_data_of_board, puzzle_id, CellData, Savegame};
+use str8ts_core::rules::box_shape;
+use str8ts_core::solver::{solve_backtrack_cancellable, compute_rows_columns, analyze_puzzle,
+    record_solver_steps, Analysis, Difficulty, SolverStep, Str8tsSolution};
+use str8ts_core::generator::{generate_puzzle, random_board};
+use str8ts_core::image_import::import_image;
+use str8ts_core::print::{render_pdf, PrintPuzzle};
+use settings::Settings;
+use user_themes::{builtin_themes, load_user_themes, ThemeWatcher};
+use translations::{explain_violation, translation, TRANSLATIONS};
+use worker::Job;
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
-sixtyfps::include_modules!();
-
-const SAVEGAME_PATH: &str = "./game_state.json";
-const P_FIXED: f64 = 0.0;
-const P_WHITE: f64 = 1.0;
+// The code generated from the .60 file doesn't pass all lints
+#[allow(dead_code, non_local_definitions, mismatched_lifetime_syntaxes, clippy::all)]
+mod ui {
+    sixtyfps::include_modules!();
+}
+use ui::*;
 
 impl Cell {
-    fn new(i: i32, value: i32, is_white: bool, is_fixed: bool) -> Cell {
+    fn new(i: i32, layout: &Layout, value: i32, is_white: bool, is_fixed: bool) -> Cell {
+        let (size, digit_count) = (layout.size(), layout.grid_size);
         Cell {
             index: i, value, is_white, is_fixed,
-            pos_x: i % 9, pos_y: i / 9,
-            small_values: ModelHandle::new(Rc::new(VecModel::from(vec![false; 9]))),
+            text: digit_text(value),
+            pos_x: i % size as i32, pos_y: i / size as i32,
+            small_values: ModelHandle::new(Rc::new(VecModel::from(vec![false; digit_count]))),
             is_editing: false,
+            is_focused: false,
+            is_peer: false,
+            is_in_compartment: false,
+            has_same_value: false,
+            highlighted_small_value: -1,
+            marking: 0,
+            small_value_markings: ModelHandle::new(Rc::new(VecModel::from(vec![0; digit_count]))),
+            marking_color: Color::default(),
+            small_value_views: ModelHandle::new(Rc::new(VecModel::from((1..=digit_count as i32)
+                .map(|value| SmallValue { text: digit_text(value), ..SmallValue::default() })
+                .collect::<Vec<_>>()))),
             is_valid_in_row: true,
             is_valid_in_straight: true,
+            is_ambiguous: false,
+            explanation: "".into(),
+            is_used: layout.is_used(i as usize),
         }
     }
+
+    fn from_board_cell(i: usize, layout: &Layout, cell: &BoardCell) -> Cell {
+        Cell::new(i as i32, layout, cell.value(), cell.is_white(), cell.is_fixed)
+    }
+
+    // Fill in what the UI shows of the value, the small values and markings. The .60 language
+    // can't index arrays, so the digit symbols and the marking colors of the theme are looked up here.
+    fn update_display(&mut self, marking_colors: &[Color]) {
+        self.text = digit_text(self.value);
+        let color = |marking: i32| if marking > 0 {
+            marking_colors.get((marking - 1) as usize).cloned().unwrap_or_default()
+        } else {
+            Color::default()
+        };
+        self.marking_color = color(self.marking);
+        let views = self.small_values.iter().zip(self.small_value_markings.iter()).enumerate()
+            .map(|(k, (is_set, marking))| SmallValue {
+                text: digit_text(k as i32 + 1), is_set, is_marked: marking > 0, marking_color: color(marking)
+            })
+            .collect::<Vec<_>>();
+        self.small_value_views = ModelHandle::new(Rc::new(VecModel::from(views)));
+    }
+
+    // The part of the cell the puzzle engine works with
+    fn board_cell(&self) -> BoardCell {
+        let kind = if self.is_white { CellKind::White } else { CellKind::Black };
+        BoardCell::new(kind, Digit::new(self.value), self.is_fixed)
+    }
+}
+
+// A value as displayed: 10-12 are A-C, nothing for empty cells (-1)
+fn digit_text(value: i32) -> sixtyfps::SharedString {
+    Digit::new(value).map(|digit| digit.to_char().to_string()).unwrap_or_default().into()
+}
+
+// UI cells of a board of the puzzle engine
+fn cells_of_board(board: &Board) -> Vec<Cell> {
+    board.cells().iter().enumerate().map(|(i, cell)| Cell::from_board_cell(i, board.layout(), cell)).collect()
+}
+
+// The UI model as board storage, so the rules can be checked without copying the cells.
+// Both the model and CellStore are defined in other crates, hence the wrapper.
+struct UiCells<'a>(&'a sixtyfps::VecModel<Cell>);
+
+impl CellStore for UiCells<'_> {
+    fn len(&self) -> usize {
+        self.0.row_count()
+    }
+
+    fn get(&self, i: usize) -> BoardCell {
+        self.0.row_data(i).board_cell()
+    }
+
+    fn set(&mut self, i: usize, board_cell: BoardCell) {
+        let mut cell = self.0.row_data(i);
+        cell.value = board_cell.value();
+        cell.text = digit_text(cell.value);
+        cell.is_white = board_cell.is_white();
+        cell.is_fixed = board_cell.is_fixed;
+        self.0.set_row_data(i, cell);
+    }
 }
 
 // Stores the UI state
@@ -50,206 +141,1420 @@ struct AppState {
     cells: Rc<sixtyfps::VecModel<Cell>>,
     main_window: sixtyfps::Weak<MainWindow>,
     was_just_solved_timer: sixtyfps::Timer,
-    editing_cell_index: Option<i8>,
+    selection: Vec<usize>, // indices of selected cells, sorted
+    focused_cell_index: Option<usize>, // cell receiving key presses, anchor for Shift-click
+    drag: Option<DragState>,
+    ctrl_pressed: bool,
+    shift_pressed: bool,
     rows_columns: Vec<Row>,
+    constraints: Constraints, // the rules, checked while playing and editing
+    layout: Layout, // the grids of the board
+    rules: RuleSet,
+    clues: Vec<Clue>, // outside clues
+    focused_clue: Option<usize>, // slot of the outside clue being edited
+    phase: Phase,
     mode: GameMode,
+    undo_stack: Vec<Savegame>, // whole game states: rules, clues, grids and cells
+    redo_stack: Vec<Savegame>,
+    settings: Settings,
+    pending_action: Option<PendingAction>,
+    builtin_themes: Vec<Theme>,
+    marking_colors: Vec<Color>, // of the current theme
+    theme_watcher: ThemeWatcher,
+    theme_reload_timer: sixtyfps::Timer,
+    analysis_job: Option<Job<Option<Analysis>>>,
+    analyzed_puzzle: Option<Board>,
+    analyzed_solution_count: Option<usize>, // once the analysis is finished, 2 for several solutions
+    board_job: Option<(&'static str, Job<BoardJobResult>)>, // busy text, job solving or generating
+    replay: Option<Replay>,
+    job_timer: sixtyfps::Timer, // polls the background jobs
+}
+
+// Represents actions that overwrite the board, and therefore may need confirmation
+#[derive(Clone, Copy, PartialEq)]
+enum PendingAction {
+    Restart,
+    NewBoard,
+    Solve,
+    Generate,
+    Load,
+    ImportImage,
+    StartPlaying // puzzle without unique solution
+}
+
+// Solutions are counted up to this number while designing a puzzle
+const MAX_COUNTED_SOLUTIONS: usize = 10;
+
+// Result of a background job that solves or generates a puzzle; None if there is no solution/puzzle
+enum BoardJobResult {
+    Solved(Option<Board>),
+    Generated(Option<Board>),
+    Recorded(Option<(Vec<SolverStep>, bool)>), // steps and whether they are incomplete, None if cancelled
+    Checked(Option<usize>), // number of solutions of the puzzle (2 for several), None if cancelled
+}
+
+// Replay of recorded solver steps on the board
+struct Replay {
+    steps: Vec<SolverStep>,
+    position: usize, // number of steps applied
+    is_running: bool,
+    speed_index: usize, // into REPLAY_SPEEDS
+    pending_steps: f64, // steps due at the current speed, but not applied yet
+    saved_board: Vec<CellData>, // board before the replay, restored afterwards
+    is_truncated: bool, // recording stopped after MAX_RECORDED_NODES, before the solver finished
+}
+
+// Replay speeds in steps per second
+const REPLAY_SPEEDS: [u32; 8] = [1, 2, 5, 10, 20, 50, 100, 500];
+
+// Recording the solver steps stops after this many values have been tried
+const MAX_RECORDED_NODES: usize = 100_000;
+
+// Interval of the timer polling the background jobs and advancing the replay
+const TICK: std::time::Duration = std::time::Duration::from_millis(50);
+
+// Represents a click-drag across cells that started at the origin cell
+struct DragState {
+    origin: usize,
+    paint_white: bool, // black/white edit mode: color that dragged-over cells are painted with
+}
+
+// Represents the phases of the game: designing the puzzle, then playing it
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Edit,
+    Play
 }
 
-// Represents game modes
-#[derive(PartialEq)]
+// Represents game modes (tools). The values are shared with the GameModes global in the .60 file.
+#[derive(Clone, Copy, PartialEq)]
+#[repr(i32)]
 enum GameMode {
-    None,
-    EditBlackWhite,
-    EditFixedNumbers,
-    PlayEnterNumbers,
-    PlayEnterSmallNumbers
+    None = 0,
+    EditBlackWhite = 1,
+    EditFixedNumbers = 2,
+    PlayEnterNumbers = 3,
+    PlayEnterSmallNumbers = 4,
+    EditClues = 5
+}
+
+impl GameMode {
+    // The one place where modes from the UI are checked: sixtyfps 0.1.3 can't declare enums in
+    // .60 files, so the UI passes the int values of GameModes
+    fn from_ui(mode: i32) -> Option<GameMode> {
+        match mode {
+            0 => Some(GameMode::None),
+            1 => Some(GameMode::EditBlackWhite),
+            2 => Some(GameMode::EditFixedNumbers),
+            3 => Some(GameMode::PlayEnterNumbers),
+            4 => Some(GameMode::PlayEnterSmallNumbers),
+            5 => Some(GameMode::EditClues),
+            _ => None
+        }
+    }
+
+    // The phase a mode can be used in (GameMode::None in both)
+    fn phase(self) -> Option<Phase> {
+        match self {
+            GameMode::None => None,
+            GameMode::EditBlackWhite | GameMode::EditFixedNumbers | GameMode::EditClues => Some(Phase::Edit),
+            GameMode::PlayEnterNumbers | GameMode::PlayEnterSmallNumbers => Some(Phase::Play)
+        }
+    }
 }
 
 impl AppState {
-    // Set UI state to a board state
-    fn set_board(&mut self, cells: &Vec<Cell>) {
+    // Number of cells per row/column of the current board
+    fn size(&self) -> usize {
+        UiCells(&self.cells).size()
+    }
+
+    // Replace the cells by an empty board if the board size or the grids change. The UI gets a
+    // new model then.
+    fn set_layout(&mut self, layout: Layout) {
+        if self.layout == layout {
+            return;
+        }
+        self.cells = Rc::new(VecModel::from(cells_of_board(&Board::empty_with_layout(layout.clone()))));
+        self.layout = layout;
+        self.selection.clear();
+        self.focused_cell_index = None;
+        self.drag = None;
+        // Outside clues belong to the old board
+        self.clues.clear();
+        self.focused_clue = None;
+        self.setup_rows_columns();
+        let main_window = self.main_window.unwrap();
+        main_window.set_cells(ModelHandle::new(self.cells.clone()));
+        self.update_layout_ui();
+    }
+
+    // Show the board size, the number of digits and the borders of the grids
+    fn update_layout_ui(&self) {
+        let main_window = self.main_window.unwrap();
+        main_window.set_board_size(self.layout.size() as i32);
+        main_window.set_digit_count(self.layout.grid_size as i32);
+        main_window.set_is_multi_grid(!self.layout.is_single());
+        let grids = if self.layout.is_single() { vec![] } else {
+            self.layout.grids.iter()
+                .map(|grid| GridPosition { row: grid.row as i32, column: grid.column as i32 })
+                .collect()
+        };
+        main_window.set_grids(ModelHandle::new(Rc::new(VecModel::from(grids))));
+        main_window.set_zoom(1.0);
+        self.update_box_borders();
+    }
+
+    // Switch between the classic rules and a variant; the rows/columns include the boxes
+    // of Sudoku-Str8ts, so they are recomputed
+    fn set_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+        self.setup_rows_columns();
+        self.update_box_borders();
+    }
+
+    // Show the thicker borders between boxes if the rules have boxes
+    fn update_box_borders(&self) {
+        let size = self.size();
+        // Boxes are only supported on boards of a single grid
+        let has_boxes = self.layout.is_single() && self.rules.has_boxes(size);
+        let (rows, columns) = match box_shape(size).filter(|_| has_boxes) {
+            Some((rows, columns)) => ((rows..size).step_by(rows).map(|k| k as i32).collect(),
+                (columns..size).step_by(columns).map(|k| k as i32).collect()),
+            None => (vec![], vec![])
+        };
+        let main_window = self.main_window.unwrap();
+        main_window.set_box_border_rows(ModelHandle::new(Rc::new(VecModel::from(rows))));
+        main_window.set_box_border_columns(ModelHandle::new(Rc::new(VecModel::from(columns))));
+        main_window.set_has_boxes(has_boxes);
+        main_window.set_boxes_available(self.layout.is_single() && box_shape(size).is_some());
+    }
+
+    // Edit phase: switch the box rule of Sudoku-Str8ts on or off
+    fn toggle_boxes(&mut self) {
+        if self.phase != Phase::Edit || self.board_job.is_some() || self.replay.is_some() {
+            return;
+        }
+        self.push_undo();
+        self.set_rules(RuleSet { boxes: !self.rules.boxes });
+        self.validate_board();
+    }
+
+    // Store a cell in the UI model, with the colors of its markings
+    fn set_cell(&self, i: usize, mut cell: Cell) {
+        cell.update_display(&self.marking_colors);
+        self.cells.set_row_data(i, cell);
+    }
+
+    // Look up the marking colors after the theme changed, and show them
+    fn update_marking_colors(&mut self) {
+        let main_window = self.main_window.unwrap();
+        self.marking_colors = main_window.get_current_theme().marking_colors.iter().collect();
+        self.update_active_marking_color();
+        for i in 0..self.cells.row_count() {
+            self.set_cell(i, self.cells.row_data(i));
+        }
+    }
+
+    // Show the color that typed small values are marked with (the first one if markings are
+    // being removed)
+    fn update_active_marking_color(&self) {
+        let main_window = self.main_window.unwrap();
+        let active_marking = main_window.get_active_marking().max(1);
+        main_window.set_active_marking_color(
+            self.marking_colors.get((active_marking - 1) as usize).cloned().unwrap_or_default());
+    }
+
+    // Set UI state to a board state of the current layout
+    fn set_board(&mut self, cells: &[Cell]) {
+        assert_eq!(cells.len(), self.cells.row_count(), "The cells don't fit the board.");
         for (i, cell) in cells.iter().enumerate() {
-            self.cells.set_row_data(i, cell.clone());
+            self.set_cell(i, cell.clone());
         }
     }
 
-    // Serialize current game state to a JSON file
-    fn save_to_file(&self, path: &str) {
-        // For each cell: save a tuple (value, is_white, is_fixed, small_values)
-        let cells_data = self.cells.iter()
-            .map(|cell| (cell.value, cell.is_white, cell.is_fixed, cell.small_values.iter().collect::<Vec<bool>>()))
-            .collect::<Vec<_>>();
+    // Snapshot of the board for the puzzle engine. Cells can't be sent to other threads, boards can.
+    fn board(&self) -> Board {
+        Board::from_store(&UiCells(&self.cells)).with_layout(self.layout.clone()).with_rules(self.rules)
+            .with_clues(self.clues.clone())
+    }
 
-        let json_data = serde_json::to_string(&cells_data)
-            .expect("Unable to save game: unable to create JSON.");
-        std::fs::write(path, json_data)
-            .expect(&format!("Unable to save game: unable to write file {}.", path));
+    // Get the state of all cells in serializable form
+    fn cell_data(&self) -> Vec<CellData> {
+        self.cells.iter()
+            .map(|cell| (cell.value, cell.is_white, cell.is_fixed, cell.small_values.iter().collect::<Vec<bool>>(),
+                cell.marking, cell.small_value_markings.iter().collect::<Vec<i32>>()))
+            .collect()
     }
 
-    // Load game state from a JSON file
-    fn load_from_file(&mut self, path: &str) {
-        let json_data = std::fs::read_to_string(path)
-            .expect(&format!("Unable to load game: unable to read file {}.", path));
-        let mut cells_data: Vec<(i32, bool, bool, Vec<bool>)> = 
-            serde_json::from_str(&json_data)
-            .expect("Unable to load game: unable to parse JSON.");
+    // Write the state of all cells from serializable form into the UI. The cells must fit the
+    // current layout.
+    fn set_cell_data(&mut self, mut cells_data: Vec<CellData>) {
+        assert_eq!(cells_data.len(), self.cells.row_count(), "The cells don't fit the board.");
         for (i, data) in cells_data.drain(..).enumerate() {
-            // Check validity of cell data
-            assert!(data.0 == -1 || (data.0 >= 1 && data.0 <= 9), "Unable to load game: invalid cell value.");
-            assert!(data.3.len() == 9, "Unable to load game: invalid small values.");
-
-            // Write data into a new cell
             let mut cell = self.cells.row_data(i);
             cell.value = data.0;
             cell.is_white = data.1;
             cell.is_fixed = data.2;
             cell.small_values = ModelHandle::new(Rc::new(VecModel::from(data.3)));
-            self.cells.set_row_data(i, cell);
+            cell.marking = data.4;
+            cell.small_value_markings = ModelHandle::new(Rc::new(VecModel::from(data.5)));
+            self.set_cell(i, cell);
+        }
+    }
+
+    // Get the whole game state: the rules, the outside clues, the grids if there are several, and
+    // for each cell a tuple (value, is_white, is_fixed, small_values, marking, small_value_markings)
+    fn savegame(&self) -> Savegame {
+        let layout = Some(self.layout.clone()).filter(|layout| !layout.is_single());
+        Savegame { rules: self.rules, clues: self.clues.clone(), layout, cells: self.cell_data() }
+    }
+
+    // Restore a whole game state. The undo history stays as it is.
+    fn set_savegame(&mut self, savegame: Savegame) {
+        self.set_layout(savegame.layout());
+        self.set_cell_data(savegame.cells);
+        self.clues = savegame.clues;
+        self.set_rules(savegame.rules);
+    }
+
+    // Serialize current game state to a JSON file
+    fn save_to_file(&self, path: &str) -> Result<(), String> {
+        let json_data = serde_json::to_string(&self.savegame()).map_err(|_| "unable to create JSON.".to_string())?;
+        std::fs::write(path, json_data).map_err(|_| format!("unable to write file {}.", path))
+    }
+
+    // Tell the player that the game couldn't be saved
+    fn show_save_error(&self, path: &str, error: &str) {
+        println!("Unable to save game to {}: {}", path, error);
+        let t = translation(&self.settings.language);
+        self.main_window.unwrap().set_status_text(t.status_save_failed.replace("{}", error).into());
+    }
+
+    // Load game state from a JSON file. The board stays as it is if the file can't be loaded.
+    fn load_from_file(&mut self, path: &str) -> Result<(), String> {
+        let json_data = std::fs::read_to_string(path).map_err(|_| format!("unable to read file {}.", path))?;
+        self.set_savegame(parse_savegame(&json_data)?);
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        Ok(())
+    }
+
+    // Tell the player that the savegame couldn't be loaded
+    fn show_load_error(&self, path: &str, error: &str) {
+        println!("Unable to load game from {}: {}", path, error);
+        let t = translation(&self.settings.language);
+        self.main_window.unwrap().set_status_text(t.status_load_failed.replace("{}", error).into());
+    }
+
+    // Remember the current game state, so that the following change can be undone
+    fn push_undo(&mut self) {
+        self.undo_stack.push(self.savegame());
+        self.redo_stack.clear();
+    }
+
+    // Undo the last change
+    fn undo(&mut self) {
+        if let Some(savegame) = self.undo_stack.pop() {
+            self.redo_stack.push(self.savegame());
+            self.set_savegame(savegame);
+            self.setup_rows_columns();
+            self.validate_board();
+        }
+    }
+
+    // Redo the last undone change
+    fn redo(&mut self) {
+        if let Some(savegame) = self.redo_stack.pop() {
+            self.undo_stack.push(self.savegame());
+            self.set_savegame(savegame);
+            self.setup_rows_columns();
+            self.validate_board();
+        }
+    }
+
+    // Apply a marking color (index into the theme's marking colors plus one, 0 = none) to the
+    // selected cells. If all of them have this marking already, it is removed instead.
+    fn mark_cells(&mut self, marking: i32) {
+        // The board can't be changed while solving/generating or replaying
+        if self.board_job.is_some() || self.replay.is_some() {
+            return;
+        }
+        let selection = self.selection.clone();
+        if selection.is_empty() {
+            return;
+        }
+        let all_marked = selection.iter().all(|&i| self.cells.row_data(i).marking == marking);
+        self.push_undo();
+        for i in selection {
+            let mut cell = self.cells.row_data(i);
+            cell.marking = if all_marked { 0 } else { marking };
+            self.set_cell(i, cell);
+        }
+    }
+
+    // Apply a marking color to a small value in the selected cells that contain it.
+    // If all of them have this marking already, it is removed instead.
+    fn mark_small_values(&mut self, value: i32, marking: i32) {
+        if self.board_job.is_some() || self.replay.is_some() {
+            return;
+        }
+        let k = (value - 1) as usize;
+        let indices = self.selection.iter().cloned()
+            .filter(|&i| self.cells.row_data(i).small_values.row_data(k))
+            .collect::<Vec<_>>();
+        if indices.is_empty() {
+            return;
+        }
+        let all_marked = indices.iter()
+            .all(|&i| self.cells.row_data(i).small_value_markings.row_data(k) == marking);
+        self.push_undo();
+        for i in indices {
+            let mut cell = self.cells.row_data(i);
+            let mut markings = cell.small_value_markings.iter().collect::<Vec<i32>>();
+            markings[k] = if all_marked { 0 } else { marking };
+            // Necessary to write the whole array, can't change a single value
+            cell.small_value_markings = ModelHandle::new(Rc::new(VecModel::from(markings)));
+            self.set_cell(i, cell);
         }
     }
 
     fn setup_rows_columns(&mut self) {
-        self.rows_columns = compute_rows_columns(&VecOrVecModel::VecModel(self.cells.clone()));
+        self.rows_columns = compute_rows_columns(&UiCells(&self.cells), &self.layout, self.rules);
+        self.constraints = Constraints::new(&self.board());
     }
 
-    // Run backtracking and write solution to UI
+    // Run backtracking in the background, the solution is written to the UI when it is found
     fn solve_puzzle(&mut self) {
-        let cells = self.cells.iter().collect::<Vec<_>>();
-        let solution = solve_backtrack(cells);
-        match solution {
-            Str8tsSolution::None => println!("No solution found."),
-            Str8tsSolution::Unique(ref cells) | Str8tsSolution::Multiple(ref cells) => {
-                for i in 0..cells.len() {
-                    self.cells.set_row_data(i, cells[i].clone());
+        let board = self.board();
+        let t = translation(&self.settings.language);
+        self.start_board_job(t.busy_solving, Job::spawn(move |token| {
+            let is_cancelled = |steps: usize| { token.set_progress(steps); token.is_cancelled() };
+            let solution = match solve_backtrack_cancellable(board, &is_cancelled) {
+                Some(Str8tsSolution::Unique(board)) => {
+                    println!("Unique solution found.");
+                    Some(board)
+                },
+                Some(Str8tsSolution::Multiple(board)) => {
+                    println!("Multiple solutions found.");
+                    Some(board)
+                },
+                _ => None
+            };
+            BoardJobResult::Solved(solution)
+        }));
+    }
+
+    // Run the generator in the background, the puzzle is shown when it is generated
+    fn generate_puzzle(&mut self) {
+        let options = self.settings.generator.clone();
+        let (layout, rules) = (self.settings.layout(), self.rules);
+        let t = translation(&self.settings.language);
+        self.start_board_job(t.busy_generating, Job::spawn(move |token| {
+            let is_cancelled = |step: usize| { token.set_progress(step); token.is_cancelled() };
+            let puzzle = generate_puzzle(&mut rand::thread_rng(), &layout, rules, options.p_fixed, options.p_white,
+                &is_cancelled);
+            BoardJobResult::Generated(puzzle)
+        }));
+    }
+
+    // Start solving/generating, show the progress and the Cancel button
+    fn start_board_job(&mut self, busy_text: &'static str, job: Job<BoardJobResult>) {
+        self.board_job = Some((busy_text, job));
+        self.main_window.unwrap().set_is_busy(true);
+        self.update_busy_text();
+    }
+
+    fn update_busy_text(&self) {
+        if let Some((text, ref job)) = self.board_job {
+            self.main_window.unwrap().set_busy_text(text.replace("{}", &job.progress().to_string()).into());
+        }
+    }
+
+    // Cancel solving/generating, the board stays as it is
+    fn cancel_board_job(&mut self) {
+        // Dropping the job cancels it
+        if self.board_job.take().is_some() {
+            let t = translation(&self.settings.language);
+            let main_window = self.main_window.unwrap();
+            main_window.set_is_busy(false);
+            main_window.set_status_text(t.status_cancelled.into());
+        }
+    }
+
+    // Write the result of a finished solve/generate job to the UI
+    fn board_job_finished(&mut self, result: BoardJobResult) {
+        let t = translation(&self.settings.language);
+        let main_window = self.main_window.unwrap();
+        main_window.set_is_busy(false);
+        match result {
+            BoardJobResult::Solved(Some(solution)) => {
+                self.push_undo();
+                for (i, solved_cell) in solution.cells().iter().enumerate() {
+                    let mut cell = self.cells.row_data(i);
+                    cell.value = solved_cell.value();
+                    self.set_cell(i, cell);
                 }
-                if let Str8tsSolution::Unique(_) = solution {
-                    println!("Unique solution found.")
-                } else {
-                    println!("Multiple solutions found.")
+                self.validate_board();
+            },
+            BoardJobResult::Solved(None) => {
+                println!("No solution found.");
+                main_window.set_status_text(t.status_no_solution.into());
+            },
+            BoardJobResult::Generated(Some(puzzle)) => {
+                println!("Puzzle with unique solution generated.");
+                self.push_undo();
+                self.set_layout(puzzle.layout().clone());
+                self.set_cell_data(cell_data_of_board(&puzzle));
+                self.clues = puzzle.clues().to_vec();
+                self.set_selection(vec![], None);
+                self.setup_rows_columns();
+                self.validate_board();
+                self.set_phase(Phase::Play, GameMode::PlayEnterNumbers);
+            },
+            BoardJobResult::Generated(None) => {
+                println!("No puzzle generated.");
+                main_window.set_status_text(t.status_generation_failed.into());
+            },
+            BoardJobResult::Recorded(Some((steps, is_truncated))) => self.start_replay(steps, is_truncated),
+            BoardJobResult::Recorded(None) => {},
+            BoardJobResult::Checked(Some(solution_count)) => self.puzzle_checked(solution_count),
+            BoardJobResult::Checked(None) => {}
+        }
+    }
+
+    // Record the steps of solving the puzzle in the background, then replay them
+    fn record_solver_steps(&mut self) {
+        let t = translation(&self.settings.language);
+        let puzzle = self.board().puzzle();
+        self.start_board_job(t.busy_recording, Job::spawn(move |token| {
+            let is_truncated = std::cell::Cell::new(false);
+            let is_cancelled = |nodes: usize| {
+                token.set_progress(nodes);
+                if nodes >= MAX_RECORDED_NODES {
+                    is_truncated.set(true);
+                }
+                token.is_cancelled() || is_truncated.get()
+            };
+            let steps = record_solver_steps(puzzle, &is_cancelled);
+            BoardJobResult::Recorded(if token.is_cancelled() { None } else { Some((steps, is_truncated.get())) })
+        }));
+    }
+
+    // Show the puzzle with all candidates as small values, then apply the steps one by one
+    fn start_replay(&mut self, steps: Vec<SolverStep>, is_truncated: bool) {
+        let saved_board = self.cell_data();
+        let digit_count = self.layout.grid_size;
+        self.set_selection(vec![], None);
+        let cells_data = saved_board.iter()
+            .map(|&(value, is_white, is_fixed, _, _, _)| {
+                let is_empty = is_white && !is_fixed;
+                (if is_fixed { value } else { -1 }, is_white, is_fixed, vec![is_empty; digit_count], 0,
+                    vec![0; digit_count])
+            })
+            .collect();
+        self.set_cell_data(cells_data);
+        self.validate_board();
+        self.replay = Some(Replay {
+            steps,
+            position: 0,
+            is_running: true,
+            speed_index: 3,
+            pending_steps: 0.0,
+            saved_board,
+            is_truncated,
+        });
+        self.main_window.unwrap().set_is_replaying(true);
+        self.update_replay_ui();
+    }
+
+    // End the replay and restore the board
+    fn stop_replay(&mut self) {
+        if let Some(replay) = self.replay.take() {
+            self.set_cell_data(replay.saved_board);
+            self.set_selection(vec![], None);
+            self.validate_board();
+            let main_window = self.main_window.unwrap();
+            main_window.set_is_replaying(false);
+            main_window.set_status_text("".into());
+        }
+    }
+
+    fn toggle_replay(&mut self) {
+        if let Some(ref mut replay) = self.replay {
+            replay.is_running = !replay.is_running;
+            replay.pending_steps = 0.0;
+        }
+        self.update_replay_ui();
+    }
+
+    fn change_replay_speed(&mut self, delta: i32) {
+        if let Some(ref mut replay) = self.replay {
+            let speed_index = replay.speed_index as i32 + delta;
+            replay.speed_index = speed_index.max(0).min(REPLAY_SPEEDS.len() as i32 - 1) as usize;
+        }
+        self.update_replay_ui();
+    }
+
+    // Timer tick: apply the steps that are due at the current speed
+    fn advance_replay(&mut self, elapsed: std::time::Duration) {
+        let steps = match self.replay {
+            Some(ref mut replay) if replay.is_running => {
+                replay.pending_steps += elapsed.as_secs_f64() * REPLAY_SPEEDS[replay.speed_index] as f64;
+                let steps = replay.pending_steps.floor();
+                replay.pending_steps -= steps;
+                steps as usize
+            },
+            _ => return
+        };
+        for _ in 0..steps {
+            self.replay_step();
+        }
+    }
+
+    // Apply the next step to the board, show what happened in the status text
+    fn replay_step(&mut self) {
+        let t = translation(&self.settings.language);
+        let step = match self.replay {
+            Some(ref mut replay) if replay.position < replay.steps.len() => {
+                replay.position += 1;
+                replay.steps[replay.position - 1].clone()
+            },
+            Some(ref mut replay) => {
+                replay.is_running = false;
+                let text = if replay.is_truncated { t.replay_finished_truncated } else { t.replay_finished };
+                self.main_window.unwrap().set_status_text(text.into());
+                self.update_replay_ui();
+                return;
+            },
+            None => return
+        };
+
+        let (p, text) = match step {
+            SolverStep::Place { cell, digit } => (cell, t.replay_place.replace("{value}", &digit.to_char().to_string())),
+            SolverStep::Eliminate { cell, ref digits } => (cell, t.replay_eliminate.replace("{values}",
+                &digits.iter().map(|digit| digit.to_char().to_string()).collect::<Vec<_>>().join(", "))),
+            SolverStep::Backtrack { cell } => (cell, t.replay_backtrack.to_string()),
+        };
+        let mut cell = self.cells.row_data(p);
+        match step {
+            SolverStep::Place { digit, .. } => cell.value = digit.get(),
+            SolverStep::Eliminate { ref digits, .. } => {
+                let mut small_values = cell.small_values.iter().collect::<Vec<bool>>();
+                for digit in digits {
+                    small_values[(digit.get() - 1) as usize] = false;
                 }
+                // Necessary to write the whole array, can't change a single value
+                cell.small_values = ModelHandle::new(Rc::new(VecModel::from(small_values)));
+            },
+            SolverStep::Backtrack { .. } => cell.value = -1,
+        }
+        self.set_cell(p, cell);
+
+        // Highlight the cell of the step: on screen, rows run along pos_x
+        let size = self.size();
+        let cell_name = t.cell_name.replace("{row}", &(p % size + 1).to_string())
+            .replace("{column}", &(p / size + 1).to_string());
+        for i in 0..self.cells.row_count() {
+            let mut cell = self.cells.row_data(i);
+            if cell.is_editing != (i == p) {
+                cell.is_editing = i == p;
+                self.set_cell(i, cell);
             }
         }
+        self.main_window.unwrap().set_status_text(text.replace("{cell}", &cell_name).into());
+        self.update_replay_ui();
     }
 
-    fn generate_puzzle(&mut self) {
-        if let Some(puzzle) = generate_puzzle()  {
-            println!("Puzzle with unique solution generated.");
-            self.set_board(&puzzle);
+    fn update_replay_ui(&self) {
+        let t = translation(&self.settings.language);
+        if let Some(ref replay) = self.replay {
+            let main_window = self.main_window.unwrap();
+            main_window.set_replay_is_running(replay.is_running);
+            main_window.set_replay_speed_text(format!("{}/s", REPLAY_SPEEDS[replay.speed_index]).into());
+            let text = format!("{} / {}", replay.position, replay.steps.len());
+            // Incomplete recordings end before the puzzle is solved
+            let text = if replay.is_truncated { t.replay_truncated.replace("{}", &text) } else { text };
+            main_window.set_replay_text(text.into());
+        }
+    }
+
+    // Reset the board to the original puzzle: keep black cells and fixed values,
+    // clear entered values, small values and markings
+    fn restart_puzzle(&mut self) {
+        self.push_undo();
+        let cells_data = self.cell_data().into_iter()
+            .map(|(value, is_white, is_fixed, small_values, _, _)|
+                (if is_fixed { value } else { -1 }, is_white, is_fixed, vec![false; small_values.len()], 0,
+                    vec![0; small_values.len()]))
+            .collect();
+        self.set_cell_data(cells_data);
+        self.set_selection(vec![], None);
+        self.validate_board();
+    }
+
+    // Replace the board by an empty board with white grids
+    fn new_empty_board(&mut self) {
+        self.push_undo();
+        let layout = self.settings.layout();
+        self.set_layout(layout.clone());
+        self.set_board(&cells_of_board(&Board::empty_with_layout(layout)));
+        self.clues.clear();
+        self.focused_clue = None;
+        self.set_selection(vec![], None);
+        self.setup_rows_columns();
+        self.validate_board();
+    }
+
+    // Check if the player has made progress (entered values, small values or markings) that would be lost
+    fn has_progress(&self) -> bool {
+        self.cells.iter().any(|cell| (!cell.is_fixed && cell.value > 0) || cell.marking > 0
+            || cell.small_values.iter().any(|v| v))
+    }
+
+    // Perform an action, or ask for confirmation first if this is enabled in the settings
+    // and something would be lost
+    fn request_action(&mut self, action: PendingAction) {
+        let confirmations = &self.settings.confirmations;
+        let t = translation(&self.settings.language);
+        let (is_enabled, text) = match action {
+            PendingAction::Restart => (confirmations.restart && self.has_progress(), t.dialog_restart),
+            PendingAction::NewBoard => (confirmations.new_board, t.dialog_new_board),
+            PendingAction::Solve => (confirmations.solve, t.dialog_solve),
+            PendingAction::Generate => (confirmations.generate && self.has_progress(), t.dialog_generate),
+            PendingAction::Load => (confirmations.load && self.has_progress(), t.dialog_load),
+            PendingAction::ImportImage => (confirmations.import_image, t.dialog_import_image),
+            PendingAction::StartPlaying => (true, t.dialog_multiple_solutions),
+        };
+        if is_enabled {
+            self.pending_action = Some(action);
+            // Only the import dialog has a placeholder, for the path of the image
+            let text = match action {
+                PendingAction::ImportImage => text.replace("{}", &self.settings.import_image_path),
+                _ => text.to_string(),
+            };
+            let main_window = self.main_window.unwrap();
+            main_window.set_confirmation_text(text.into());
+            // The warning about a puzzle without unique solution has no setting to switch it off
+            main_window.set_confirmation_can_be_disabled(action != PendingAction::StartPlaying);
+            main_window.set_is_confirming(true);
         } else {
-            println!("No puzzle generated.")
+            self.perform_action(action);
+        }
+    }
+
+    // Handle the answer to a confirmation dialog; optionally disable the confirmation for this action
+    fn confirmation_answered(&mut self, is_confirmed: bool, dont_ask_again: bool) {
+        self.main_window.unwrap().set_is_confirming(false);
+        if let Some(action) = self.pending_action.take() {
+            if is_confirmed {
+                if dont_ask_again {
+                    let confirmations = &mut self.settings.confirmations;
+                    match action {
+                        PendingAction::Restart => confirmations.restart = false,
+                        PendingAction::NewBoard => confirmations.new_board = false,
+                        PendingAction::Solve => confirmations.solve = false,
+                        PendingAction::Generate => confirmations.generate = false,
+                        PendingAction::Load => confirmations.load = false,
+                        PendingAction::ImportImage => confirmations.import_image = false,
+                        PendingAction::StartPlaying => {},
+                    }
+                    self.settings.save(&settings::settings_path());
+                    self.update_settings_ui();
+                }
+                self.perform_action(action);
+            }
         }
     }
 
+    fn perform_action(&mut self, action: PendingAction) {
+        match action {
+            PendingAction::Restart => self.restart_puzzle(),
+            PendingAction::NewBoard => {
+                self.new_empty_board();
+                self.set_phase(Phase::Edit, GameMode::EditBlackWhite);
+            },
+            PendingAction::Solve => self.solve_puzzle(),
+            PendingAction::Generate => self.generate_puzzle(),
+            PendingAction::StartPlaying => self.set_phase(Phase::Play, GameMode::PlayEnterNumbers),
+            PendingAction::Load => {
+                let undo_entry = self.savegame();
+                let undo_stack = std::mem::take(&mut self.undo_stack);
+                let path = self.settings.savegame_path.clone();
+                let result = self.load_from_file(&path);
+                self.undo_stack = undo_stack;
+                if let Err(error) = result {
+                    self.show_load_error(&path, &error);
+                    return;
+                }
+                // Loading can be undone as well
+                self.undo_stack.push(undo_entry);
+                self.set_selection(vec![], None);
+                self.setup_rows_columns();
+                self.validate_board();
+                self.set_phase(self.loaded_phase(), GameMode::None);
+            },
+            PendingAction::ImportImage => self.import_image(),
+        }
+    }
+
+    // Replace the board by the puzzle recognized in the image given in the settings. It opens
+    // in the edit phase, so that misread cells and numbers can be corrected.
+    fn import_image(&mut self) {
+        let t = translation(&self.settings.language);
+        let path = self.settings.import_image_path.clone();
+        let board = match import_image(std::path::Path::new(&path), None) {
+            Ok(board) => board,
+            Err(error) => {
+                println!("Unable to import image {}: {}", path, error);
+                self.main_window.unwrap().set_status_text(t.status_import_failed.replace("{}", &error).into());
+                return;
+            }
+        };
+        self.push_undo();
+        self.set_layout(Layout::single(board.size()));
+        self.set_board(&cells_of_board(&board));
+        self.clues.clear();
+        self.focused_clue = None;
+        self.set_rules(RuleSet::classic());
+        self.set_selection(vec![], None);
+        self.set_phase(Phase::Edit, GameMode::EditBlackWhite);
+        self.main_window.unwrap().set_status_text(t.status_imported.into());
+    }
+
+    // Write the board as a printable PDF to the file given in the settings: the puzzle with
+    // the numbers and small numbers entered so far, for continuing on paper
+    fn print_puzzle(&self) {
+        let t = translation(&self.settings.language);
+        let path = &self.settings.print_path;
+        let board = self.board();
+        let pencil_marks = self.cells.iter()
+            .map(|cell| cell.small_values.iter().enumerate()
+                .filter(|&(_, is_set)| is_set)
+                .filter_map(|(k, _)| Digit::new(k as i32 + 1))
+                .collect())
+            .collect();
+        let puzzle = PrintPuzzle {
+            id: puzzle_id(&board),
+            board,
+            pencil_marks,
+            title: "Str8ts".into(),
+            difficulty: None,
+            solution: None,
+        };
+        let result = render_pdf(&[puzzle], 1)
+            .and_then(|pdf| std::fs::write(path, pdf).map_err(|error| error.to_string()));
+        let status = match result {
+            Ok(()) => t.status_printed.replace("{}", path),
+            Err(error) => {
+                println!("Unable to print to {}: {}", path, error);
+                t.status_print_failed.replace("{}", &error)
+            }
+        };
+        self.main_window.unwrap().set_status_text(status.into());
+    }
+
+    // Switch to a phase and a mode of that phase
+    fn set_phase(&mut self, phase: Phase, mode: GameMode) {
+        self.phase = phase;
+        self.main_window.unwrap().set_is_playing(phase == Phase::Play);
+        self.main_window.unwrap().set_status_text("".into());
+        self.set_mode(mode);
+        // The puzzle is only analyzed while designing it, validating updates the analysis
+        self.analyzed_puzzle = None;
+        self.validate_board();
+    }
+
+    // Phase for a loaded board: boards with black cells or fixed numbers are played, empty boards are edited
+    fn loaded_phase(&self) -> Phase {
+        if self.cells.iter().any(|cell| !cell.is_white || cell.is_fixed) { Phase::Play } else { Phase::Edit }
+    }
+
+    // Transition from the edit phase to the play phase: the puzzle must be valid and have
+    // a solution; if the solution is not unique, the player is asked to confirm
+    fn finish_editing(&mut self) {
+        if self.board_job.is_some() {
+            return;
+        }
+        let t = translation(&self.settings.language);
+        if !self.validate_board() {
+            self.main_window.unwrap().set_status_text(t.status_invalid.into());
+            return;
+        }
+        // Only the puzzle itself is checked, not values entered while editing. The analysis has
+        // counted its solutions already if it is finished, otherwise they are counted in the background.
+        if let Some(solution_count) = self.analyzed_solution_count {
+            self.puzzle_checked(solution_count);
+            return;
+        }
+        let puzzle = self.board().puzzle();
+        self.start_board_job(t.busy_checking, Job::spawn(move |token| {
+            let is_cancelled = |steps: usize| { token.set_progress(steps); token.is_cancelled() };
+            let solution_count = solve_backtrack_cancellable(puzzle, &is_cancelled).map(|solution| match solution {
+                Str8tsSolution::None => 0,
+                Str8tsSolution::Unique(_) => 1,
+                Str8tsSolution::Multiple(_) => 2,
+            });
+            BoardJobResult::Checked(solution_count)
+        }));
+    }
+
+    // Play the puzzle if it has a unique solution, ask first if it has several
+    fn puzzle_checked(&mut self, solution_count: usize) {
+        let t = translation(&self.settings.language);
+        match solution_count {
+            0 => self.main_window.unwrap().set_status_text(t.status_no_solution.into()),
+            1 => self.set_phase(Phase::Play, GameMode::PlayEnterNumbers),
+            _ => self.request_action(PendingAction::StartPlaying)
+        }
+    }
+
+    // (Re)load the user themes and offer them after the built-in themes. The current
+    // theme stays selected if it still exists.
+    fn reload_themes(&mut self) {
+        let main_window = self.main_window.unwrap();
+        let current_name = main_window.get_current_theme().name;
+        let mut themes = self.builtin_themes.clone();
+        themes.extend(load_user_themes(self.theme_watcher.dir(), &self.builtin_themes));
+        let index = themes.iter().position(|theme| theme.name == current_name).unwrap_or(0);
+        main_window.set_current_theme(themes[index].clone());
+        main_window.set_themes(ModelHandle::new(Rc::new(VecModel::from(themes))));
+        self.update_marking_colors();
+    }
+
+    // Write the settings to the UI properties
+    fn update_settings_ui(&self) {
+        let main_window = self.main_window.unwrap();
+        let settings = &self.settings;
+        // Select the theme by name, keep the current theme if it does not exist
+        if let Some(theme) = main_window.get_themes().iter().find(|theme| theme.name == settings.theme.as_str()) {
+            main_window.set_current_theme(theme);
+        }
+        main_window.set_language_index(TRANSLATIONS.iter().position(|t| t.code == settings.language).unwrap_or(0) as i32);
+        main_window.set_strings(ui_strings(translation(&settings.language)));
+        main_window.set_gameplay_settings(GameplaySettings {
+            auto_remove_small_values: settings.auto_remove_small_values,
+            strict_check: settings.strict_check,
+        });
+        main_window.set_generator_black_percentage(((1.0 - settings.generator.p_white) * 100.0).round() as i32);
+        main_window.set_new_board_size(settings.board_size as i32);
+        main_window.set_new_board_samurai(settings.samurai);
+        main_window.set_highlight_options(HighlightOptions {
+            peers: settings.highlights.peers,
+            compartment: settings.highlights.compartment,
+            same_value: settings.highlights.same_value,
+        });
+        let confirmations = &settings.confirmations;
+        main_window.set_confirmation_settings(ConfirmationSettings {
+            restart: confirmations.restart,
+            new_board: confirmations.new_board,
+            solve: confirmations.solve,
+            generate: confirmations.generate,
+            load: confirmations.load,
+            import_image: confirmations.import_image,
+        });
+    }
+
+    // Read back the settings changed in the UI, apply and save them
+    fn settings_changed(&mut self) {
+        let main_window = self.main_window.unwrap();
+        let settings = &mut self.settings;
+        settings.theme = main_window.get_current_theme().name.to_string();
+        let language_index = main_window.get_language_index() as usize;
+        settings.language = TRANSLATIONS.get(language_index).unwrap_or(&TRANSLATIONS[0]).code.to_string();
+        let gameplay_settings = main_window.get_gameplay_settings();
+        settings.auto_remove_small_values = gameplay_settings.auto_remove_small_values;
+        settings.strict_check = gameplay_settings.strict_check;
+        settings.generator.p_white = 1.0 - main_window.get_generator_black_percentage() as f64 / 100.0;
+        settings.board_size = main_window.get_new_board_size() as usize;
+        settings.samurai = main_window.get_new_board_samurai();
+        let highlight_options = main_window.get_highlight_options();
+        settings.highlights.peers = highlight_options.peers;
+        settings.highlights.compartment = highlight_options.compartment;
+        settings.highlights.same_value = highlight_options.same_value;
+        let ui_confirmations = main_window.get_confirmation_settings();
+        let confirmations = &mut settings.confirmations;
+        confirmations.restart = ui_confirmations.restart;
+        confirmations.new_board = ui_confirmations.new_board;
+        confirmations.solve = ui_confirmations.solve;
+        confirmations.generate = ui_confirmations.generate;
+        confirmations.load = ui_confirmations.load;
+        confirmations.import_image = ui_confirmations.import_image;
+        self.settings.save(&settings::settings_path());
+
+        main_window.set_strings(ui_strings(translation(&self.settings.language)));
+        self.update_marking_colors();
+        // Revalidating updates the highlights and the analysis text in the new language
+        self.analyzed_puzzle = None;
+        self.validate_board();
+    }
+
     // Check if board is valid, and mark invalid cells along the way
     fn validate_board(&mut self) -> bool {
+        let t = translation(&self.settings.language);
         // Clone cells from UI with valid values set to true
-        let mut cell_data = (0..81).map(|index| {
+        let mut cell_data = (0..self.cells.row_count()).map(|index| {
             let mut cell = self.cells.row_data(index);
             cell.is_valid_in_row = true;
             cell.is_valid_in_straight = true;
             cell
         }).collect::<Vec<_>>();
+        let mut explanations = vec![vec![]; cell_data.len()];
 
-        let all_cells = VecOrVecModel::VecModel(self.cells.clone());
-        // Validate each row and handle the invalid cells if some exist
-        for row in &self.rows_columns {
-            if let Some((multiple_occurrences, invalid_straights))
-                    = row.validate(&all_cells) {
-                // Mark cells that are duplicate in the row/column
-                for occurence_indices in multiple_occurrences {
-                    for p in occurence_indices {
-                        cell_data[p].is_valid_in_row = false;
-                    }
-                }
-                // Mark all cells of straights that are invalid
-                for straight in invalid_straights {
-                    for p in straight {
-                        cell_data[p].is_valid_in_straight = false;
-                    }
+        // Check each constraint and handle the invalid cells if some exist
+        for (k, violation) in self.constraints.validate(&UiCells(&self.cells)) {
+            let explanation = explain_violation(t, &self.constraints.explain(k, &violation));
+            for &p in violation.cells() {
+                match violation {
+                    // Mark cells that are duplicate in the row/column
+                    Violation::Duplicate { .. } => cell_data[p].is_valid_in_row = false,
+                    // Mark all cells of straights that are invalid, and the cells of broken outside clues
+                    Violation::Straight { .. } | Violation::Clue { .. } => cell_data[p].is_valid_in_straight = false,
                 }
+                explanations[p].push(explanation.clone());
             }
         }
+        for (cell, explanation) in cell_data.iter_mut().zip(explanations) {
+            cell.explanation = explanation.join(" · ").into();
+        }
         // Write back updated cells to UI
         self.set_board(&cell_data);
+        self.update_highlights();
+        self.update_clue_slots();
         
         // Determine and return whether overall board is valid
-        cell_data.iter().all(|cell| cell.is_valid_in_row && cell.is_valid_in_straight)
+        let is_valid = cell_data.iter().all(|cell| cell.is_valid_in_row && cell.is_valid_in_straight);
+        self.update_analysis(is_valid);
+        is_valid
     }
 
-    // Handle a click on a cell
-    fn cell_clicked(&mut self, p: i8) -> bool {
-        let mut cell = self.cells.row_data(p as usize);
-        
+    // Check if entering the digit into the cells breaks a rule. Only the rules of these cells
+    // are checked, so that mistakes elsewhere on the board don't block entering numbers.
+    fn breaks_rules(&self, cells: &[usize], digit: Digit) -> bool {
+        let mut board = self.board();
+        for &i in cells {
+            let kind = board[i].kind;
+            // The candidates of a cell are computed without its own digit
+            board[i] = BoardCell::new(kind, None, false);
+            if !self.constraints.candidates(i, &board).contains(&digit) {
+                return true;
+            }
+            board[i] = BoardCell::new(kind, Some(digit), false);
+        }
+        false
+    }
+
+    // Edit phase: (re)start analyzing the puzzle in the background if it has changed.
+    // A running analysis of an outdated puzzle is cancelled.
+    fn update_analysis(&mut self, is_valid: bool) {
+        let t = translation(&self.settings.language);
+        let main_window = self.main_window.unwrap();
+        if self.phase != Phase::Edit {
+            self.analysis_job = None;
+            self.set_ambiguous_cells(&[]);
+            main_window.set_analysis_text("".into());
+            return;
+        }
+
+        let puzzle = self.board().puzzle();
+        if self.analyzed_puzzle.as_ref() == Some(&puzzle) {
+            return;
+        }
+        self.analyzed_puzzle = Some(puzzle.clone());
+        self.analyzed_solution_count = None;
+        self.set_ambiguous_cells(&[]);
+        // The solver does not check the fixed numbers, so invalid puzzles are not analyzed
+        if !is_valid {
+            self.analysis_job = None;
+            main_window.set_analysis_text(t.status_invalid.into());
+            return;
+        }
+
+        main_window.set_analysis_text(t.analysis_checking.into());
+        self.analysis_job = Some(Job::spawn(move |token| {
+            analyze_puzzle(puzzle, MAX_COUNTED_SOLUTIONS, &|_| token.is_cancelled())
+        }));
+    }
+
+    // Show the result of the puzzle analysis
+    fn show_analysis(&mut self, analysis: &Analysis) {
+        let t = translation(&self.settings.language);
+        let text = match (analysis.solution_count, analysis.has_more_solutions) {
+            (0, _) => t.status_no_solution.to_string(),
+            (1, false) => {
+                let difficulty = match analysis.difficulty {
+                    Some(Difficulty::Easy) => t.difficulty_easy,
+                    Some(Difficulty::Medium) => t.difficulty_medium,
+                    Some(Difficulty::Hard) => t.difficulty_hard,
+                    Some(Difficulty::Extreme) | None => t.difficulty_extreme,
+                };
+                t.analysis_unique.replace("{}", difficulty)
+            },
+            (count, false) => t.analysis_multiple.replace("{}", &count.to_string()),
+            (count, true) => t.analysis_many.replace("{}", &count.to_string()),
+        };
+        self.main_window.unwrap().set_analysis_text(text.into());
+        self.set_ambiguous_cells(&analysis.differing_cells);
+    }
+
+    // Mark the cells that differ between two solutions of the puzzle
+    fn set_ambiguous_cells(&mut self, indices: &[usize]) {
+        for i in 0..self.cells.row_count() {
+            let mut cell = self.cells.row_data(i);
+            let is_ambiguous = indices.contains(&i);
+            if cell.is_ambiguous != is_ambiguous {
+                cell.is_ambiguous = is_ambiguous;
+                self.set_cell(i, cell);
+            }
+        }
+    }
+
+    // Pick up the results of finished background jobs
+    fn poll_jobs(&mut self) {
+        if let Some(result) = self.analysis_job.as_ref().and_then(|job| job.poll()) {
+            self.analysis_job = None;
+            if let Some(analysis) = result {
+                self.analyzed_solution_count = Some(match (analysis.solution_count, analysis.has_more_solutions) {
+                    (count, false) => count.min(2),
+                    (_, true) => 2,
+                });
+                self.show_analysis(&analysis);
+            }
+        }
+        if let Some(result) = self.board_job.as_ref().and_then(|(_, job)| job.poll()) {
+            self.board_job = None;
+            self.board_job_finished(result);
+        } else {
+            self.update_busy_text();
+        }
+    }
+
+    // Highlight the row/column, the compartments and the same digit as the focused cell,
+    // as far as enabled in the highlight options
+    fn update_highlights(&mut self) {
+        let options = self.settings.highlights.clone();
+        let focused = self.focused_cell_index.map(|f| (f, self.cells.row_data(f).value));
+        // Cells in the rows/columns of the focused cell (of all grids it belongs to), and in their straights
+        let mut peers = vec![];
+        let mut compartments = vec![];
+        if let Some((f, _)) = focused {
+            for row in self.units_of(f).into_iter().filter(|row| !matches!(row.unit(), Unit::Box(_))) {
+                peers.extend(row.cells().iter().cloned());
+                compartments.extend(row.straight_of(f).into_iter().flatten());
+            }
+        }
+
+        for i in 0..self.cells.row_count() {
+            let mut cell = self.cells.row_data(i);
+            let (is_peer, is_in_compartment, has_same_value, highlighted_small_value) = match focused {
+                Some((f, value)) => {
+                    let same_value = if options.same_value && value > 0 { value } else { -1 };
+                    (options.peers && i != f && peers.contains(&i),
+                    options.compartment && i != f && compartments.contains(&i),
+                    i != f && same_value > 0 && cell.value == same_value,
+                    if same_value > 0 && cell.value <= 0 && cell.small_values.row_data((same_value - 1) as usize) 
+                        { same_value } else { -1 })
+                },
+                None => (false, false, false, -1)
+            };
+            if cell.is_peer != is_peer || cell.is_in_compartment != is_in_compartment
+                    || cell.has_same_value != has_same_value || cell.highlighted_small_value != highlighted_small_value {
+                cell.is_peer = is_peer;
+                cell.is_in_compartment = is_in_compartment;
+                cell.has_same_value = has_same_value;
+                cell.highlighted_small_value = highlighted_small_value;
+                self.set_cell(i, cell);
+            }
+        }
+    }
+
+    // Rows, columns and boxes containing a cell. A cell shared by several grids is in a row and
+    // a column of each of them.
+    fn units_of(&self, cell_index: usize) -> Vec<&Row> {
+        self.rows_columns.iter().filter(|row| row.cells().contains(&cell_index)).collect()
+    }
+
+    // Check if a cell can be selected in the current game mode; unused cells between grids never are
+    fn is_selectable(&self, p: usize) -> bool {
+        let cell = self.cells.row_data(p);
+        if !cell.is_used {
+            return false;
+        }
         match self.mode {
-            // Edit black/white mode: switch black/white re-setup row/column structure and revalidate
-            GameMode::EditBlackWhite => {
-                cell.is_white = !cell.is_white;
-                self.cells.set_row_data(p as usize, cell.clone());
-                self.setup_rows_columns();
-                self.validate_board();
+            GameMode::EditBlackWhite | GameMode::EditFixedNumbers => true,
+            GameMode::PlayEnterNumbers | GameMode::PlayEnterSmallNumbers => cell.is_white && !cell.is_fixed,
+            GameMode::None | GameMode::EditClues => false
+        }
+    }
+
+    // Replace the selection and the focused cell, update the cells in the UI where necessary
+    fn set_selection(&mut self, mut selection: Vec<usize>, focused_cell_index: Option<usize>) {
+        selection.retain(|&p| self.is_selectable(p));
+        selection.sort_unstable();
+        selection.dedup();
+
+        for p in 0..self.cells.row_count() {
+            let mut cell = self.cells.row_data(p);
+            let is_editing = selection.binary_search(&p).is_ok();
+            let is_focused = focused_cell_index == Some(p);
+            if cell.is_editing != is_editing || cell.is_focused != is_focused {
+                cell.is_editing = is_editing;
+                cell.is_focused = is_focused;
+                self.set_cell(p, cell);
+            }
+        }
+        self.selection = selection;
+        self.focused_cell_index = focused_cell_index;
+        self.update_highlights();
+    }
+
+    // Paint cells black or white, then re-setup row/column structure and revalidate
+    fn set_white(&mut self, indices: &[usize], is_white: bool) {
+        let mut changed = false;
+        for &p in indices.iter().filter(|&&p| self.layout.is_used(p)) {
+            let mut cell = self.cells.row_data(p);
+            if cell.is_white != is_white {
+                cell.is_white = is_white;
+                self.set_cell(p, cell);
+                changed = true;
+            }
+        }
+        if changed {
+            self.setup_rows_columns();
+            self.validate_board();
+        }
+    }
+
+    // Handle a click on a cell: plain click selects the cell only, Ctrl-click
+    // toggles it in the selection, Shift-click adds the rectangle from the focused cell
+    fn cell_clicked(&mut self, p: usize) -> bool {
+        if self.replay.is_some() {
+            return true;
+        }
+
+        // The click that ends a drag has already been handled while dragging
+        if let Some(drag) = self.drag.take() {
+            if drag.origin == p {
+                return true;
+            }
+        }
+
+        match self.mode {
+            // Edit black/white mode: plain click switches black/white
+            GameMode::EditBlackWhite if !self.ctrl_pressed && !self.shift_pressed => {
+                self.set_selection(vec![], Some(p));
+                let is_white = self.cells.row_data(p).is_white;
+                self.push_undo();
+                self.set_white(&[p], !is_white);
             },
-            // Edit fixed/non-fixed/small numbers modes: enter editing mode
-            GameMode::EditFixedNumbers | GameMode::PlayEnterNumbers | GameMode::PlayEnterSmallNumbers => {
-                // Reset currently editing cell
-                if let Some(index) = self.editing_cell_index {
-                    let mut editing_cell = self.cells.row_data(index as usize);
-                    editing_cell.is_editing = false;
-                    self.cells.set_row_data(index as usize, editing_cell);
-                    self.editing_cell_index = None;
-                }
-                // If new cell can be edited, set it to editing mode
-                if !cell.is_fixed && cell.is_white || self.mode == GameMode::EditFixedNumbers {
-                    cell.is_editing = !cell.is_editing;
-                    if cell.is_editing {
-                        self.editing_cell_index = Some(p);
+            GameMode::None => {},
+            // Otherwise: change the selection
+            _ => {
+                let selection = if self.shift_pressed {
+                    let anchor = self.focused_cell_index.unwrap_or(p);
+                    let mut selection = self.selection.clone();
+                    selection.extend(rectangle_indices(self.size(), anchor, p));
+                    selection
+                } else if self.ctrl_pressed {
+                    let mut selection = self.selection.clone();
+                    match selection.iter().position(|&q| q == p) {
+                        Some(k) => { selection.remove(k); },
+                        None => selection.push(p)
                     }
+                    selection
+                } else if self.selection == [p] {
+                    // Clicking the only selected cell again deselects it
+                    vec![]
+                } else {
+                    vec![p]
+                };
+                self.set_selection(selection, Some(p));
+            }
+        }
+        true
+    }
+
+    // Handle dragging from cell p to the cell (dx, dy) cells away on screen:
+    // extends the selection, or paints cells in black/white edit mode
+    fn cell_dragged(&mut self, p: usize, dx: i32, dy: i32) {
+        if self.replay.is_some() {
+            return;
+        }
+
+        // On screen, x runs along pos_y and y runs along pos_x
+        let size = self.size() as i32;
+        let (pos_x, pos_y) = ((p as i32 % size) + dy, (p as i32 / size) + dx);
+        if pos_x < 0 || pos_x >= size || pos_y < 0 || pos_y >= size {
+            return;
+        }
+        let target = (size * pos_y + pos_x) as usize;
+        let is_continued = matches!(self.drag, Some(ref drag) if drag.origin == p);
+        if target == p && !is_continued {
+            return;
+        }
+
+        match self.mode {
+            GameMode::None => {},
+            // Paint all dragged-over cells in the inverse color of the origin cell
+            GameMode::EditBlackWhite => {
+                let paint_white = match self.drag {
+                    Some(ref drag) if is_continued => drag.paint_white,
+                    _ => !self.cells.row_data(p).is_white
+                };
+                // The whole drag is undone at once
+                if !is_continued {
+                    self.push_undo();
                 }
-                self.cells.set_row_data(p as usize, cell.clone());
+                self.drag = Some(DragState { origin: p, paint_white });
+                self.set_white(&[p, target], paint_white);
             },
-            _ => {}
+            // Add all dragged-over cells to the selection
+            _ => {
+                let mut selection = if is_continued || self.ctrl_pressed { self.selection.clone() } else { vec![p] };
+                selection.push(target);
+                self.drag = Some(DragState { origin: p, paint_white: false });
+                self.set_selection(selection, Some(p));
+            }
         }
-        true
     }
 
-    // Handle keyboard inputs on cells
+    // Keep track of the modifier keys, which change the meaning of clicks
+    fn update_modifiers(&mut self, e: &KeyEvent, is_pressed: bool) {
+        self.ctrl_pressed = if e.text == "\u{11}" { is_pressed } else { e.modifiers.control };
+        self.shift_pressed = if e.text == "\u{10}" { is_pressed } else { e.modifiers.shift };
+    }
+
+    // Select the straight (compartment) of the focused cell in its row or column on screen
+    fn select_compartment(&mut self, is_row: bool) {
+        if let Some(p) = self.focused_cell_index {
+            let selection = self.units_of(p).into_iter()
+                .find(|row| match row.unit() {
+                    Unit::Row(_) => is_row,
+                    Unit::Column(_) => !is_row,
+                    Unit::Box(_) => false,
+                })
+                .and_then(|row| row.straight_of(p))
+                .cloned().unwrap_or_default();
+            self.set_selection(selection, Some(p));
+        }
+    }
+
+    // Handle keyboard inputs on cells. Values and small numbers are applied to all selected cells.
     fn cell_key_pressed(&mut self, p: i32, e: KeyEvent) -> Option<bool> {
-        // Only proceed if game is in number editing mode
+        self.update_modifiers(&e, true);
+        let p = p as usize;
+
+        // The board can't be changed while solving/generating or replaying
+        if self.board_job.is_some() || self.replay.is_some() {
+            return None;
+        }
+
+        // Undo (Ctrl+Z) and redo (Ctrl+Y)
+        match e.text.as_str() {
+            "z" | "Z" | "\u{1a}" if self.ctrl_pressed => { self.undo(); return None; },
+            "y" | "Y" | "\u{19}" if self.ctrl_pressed => { self.redo(); return None; },
+            _ => {}
+        }
+
+        // Keys that change the selection
+        match e.text.as_str() {
+            "h" => { self.select_compartment(true); return None; },
+            "v" => { self.select_compartment(false); return None; },
+            "\u{1b}" => { self.set_selection(vec![], Some(p)); return None; },
+            _ => {}
+        }
+
+        // Edit black/white mode: paint the selection black (b), white (w) or invert it (space)
+        if self.mode == GameMode::EditBlackWhite {
+            let selection = self.selection.clone();
+            if !selection.is_empty() && (e.text == "b" || e.text == "w" || e.text == " ") {
+                self.push_undo();
+            }
+            match e.text.as_str() {
+                "b" => self.set_white(&selection, false),
+                "w" => self.set_white(&selection, true),
+                " " => for i in selection {
+                    let is_white = self.cells.row_data(i).is_white;
+                    self.set_white(&[i], !is_white);
+                },
+                _ => {}
+            }
+            return None;
+        }
+
+        // Only proceed if game is in number editing mode and some cell is selected
         match self.mode {
             GameMode::EditFixedNumbers | GameMode::PlayEnterNumbers | GameMode::PlayEnterSmallNumbers => {},
             _ => return None
         }
-
-        // Only proceed if the cell is in editing mode
-        let mut cell = self.cells.row_data(p as usize);
-        if !cell.is_editing {
+        if self.selection.is_empty() {
             return None;
         }
 
-        // Only process digits 1-9, backspace, del keys
-        let new_value = if let Ok(k) = e.text.parse::<i32>() {
-            Some(k).filter(|&k| k >= 1 && k <= 9)
+        // Only process digits of the board (1-9, then A-C), backspace, del keys
+        let mut chars = e.text.chars();
+        let digit = match (chars.next(), chars.next()) {
+            (Some(c), None) => Digit::from_char(c).filter(|digit| digit.get() <= self.layout.grid_size as i32),
+            _ => None
+        };
+        let new_value = if let Some(digit) = digit {
+            Some(digit.get())
         } 
         else if e.text == "\u{7}" || e.text == "\u{7f}" {
             Some(-1)
         }
         else { None };
         
+        // Small numbers mode with candidate marking: color the typed small value in the selected cells
+        let main_window = self.main_window.unwrap();
+        if self.mode == GameMode::PlayEnterSmallNumbers && main_window.get_mark_small_values() {
+            if let Some(val) = new_value.filter(|&val| val > 0) {
+                self.mark_small_values(val, main_window.get_active_marking());
+            }
+            return None;
+        }
+
         if let Some(val) = new_value {
-            // Enter cell value (fixed or non-fixed)
+            let selection = self.selection.clone();
+            // Strict check: refuse the number if it breaks a rule
+            if self.mode == GameMode::PlayEnterNumbers && self.settings.strict_check
+                    && digit.is_some_and(|digit| self.breaks_rules(&selection, digit)) {
+                return Some(false);
+            }
+            self.push_undo();
+            // Enter cell value (fixed or non-fixed) into all selected cells
             if self.mode == GameMode::EditFixedNumbers || self.mode == GameMode::PlayEnterNumbers {
-                cell.value = val;
-                cell.is_editing = false;
-                cell.is_fixed = if self.mode == GameMode::EditFixedNumbers && val > 0 {true} else {false};
-                self.editing_cell_index = None;
+                for &i in &selection {
+                    let mut cell = self.cells.row_data(i);
+                    cell.value = val;
+                    cell.is_fixed = self.mode == GameMode::EditFixedNumbers && val > 0;
+                    self.set_cell(i, cell);
+                }
+                if self.mode == GameMode::PlayEnterNumbers && val > 0 && self.settings.auto_remove_small_values {
+                    for &i in &selection {
+                        self.remove_small_value_from_peers(i, val);
+                    }
+                }
+                self.set_selection(vec![], self.focused_cell_index);
             } 
-            // Enter small number
-            else if self.mode == GameMode::PlayEnterSmallNumbers && val > 0 {
-                let mut small_numbers = cell.small_values.iter().collect::<Vec<bool>>();
-                small_numbers[(val - 1) as usize] = !small_numbers[(val - 1) as usize];
-                // Necessary to write the whole array, can't change a single value
-                cell.small_values = ModelHandle::new(Rc::new(VecModel::from(small_numbers)));
+            // Toggle small number: set it in all selected cells, or clear it if all of them have it already.
+            // Backspace/del clears all small numbers.
+            else if self.mode == GameMode::PlayEnterSmallNumbers {
+                let all_have_value = val > 0 && selection.iter()
+                    .all(|&i| self.cells.row_data(i).small_values.row_data((val - 1) as usize));
+                for &i in &selection {
+                    let mut cell = self.cells.row_data(i);
+                    let mut small_numbers = cell.small_values.iter().collect::<Vec<bool>>();
+                    if val > 0 {
+                        small_numbers[(val - 1) as usize] = !all_have_value;
+                    } else {
+                        small_numbers = vec![false; small_numbers.len()];
+                    }
+                    // Necessary to write the whole array, can't change a single value
+                    cell.small_values = ModelHandle::new(Rc::new(VecModel::from(small_numbers)));
+                    self.set_cell(i, cell);
+                }
             }
-            self.cells.set_row_data(p as usize, cell)
         }
 
         // Determine and return if puzzle is solved (board is complete and valid)
@@ -258,33 +1563,200 @@ impl AppState {
         Some(is_valid && is_complete)
     }
 
+    // Remove a small value from all cells in the rows and columns (and the box in Sudoku-Str8ts)
+    // of a cell
+    fn remove_small_value_from_peers(&mut self, cell_index: usize, value: i32) {
+        let peers = self.units_of(cell_index).iter()
+            .flat_map(|row| row.cells().iter().cloned())
+            .collect::<Vec<_>>();
+        for i in peers {
+            let mut cell = self.cells.row_data(i);
+            if cell.small_values.row_data((value - 1) as usize) {
+                let mut small_numbers = cell.small_values.iter().collect::<Vec<bool>>();
+                small_numbers[(value - 1) as usize] = false;
+                // Necessary to write the whole array, can't change a single value
+                cell.small_values = ModelHandle::new(Rc::new(VecModel::from(small_numbers)));
+                self.set_cell(i, cell);
+            }
+        }
+    }
+
+    // Handle key releases on cells
+    fn cell_key_released(&mut self, e: KeyEvent) {
+        self.update_modifiers(&e, false);
+    }
+
     // Check if board is complete (no empty white cells)
     fn is_complete(&self) -> bool {
         !self.cells.iter().any(|cell| cell.value <= 0 && cell.is_white)
     }
 
-    // Set game mode (editing board/entering numbers for playing)
-    fn set_mode(&mut self, mode: &str) {
-        self.mode = match mode {
-            "edit-black-white" => { 
-                // Make editing cell non-editing
-                if let Some(editing_cell_index) = self.editing_cell_index {
-                    let mut cell = self.cells.row_data(editing_cell_index as usize);
-                    cell.is_editing = false;
-                    self.cells.set_row_data(editing_cell_index as usize, cell);
-                    self.editing_cell_index = None;
+    // Set game mode (editing board/entering numbers for playing). Only modes of the current phase are allowed.
+    fn set_mode(&mut self, mode: GameMode) {
+        if matches!(mode.phase(), Some(phase) if phase != self.phase) {
+            return;
+        }
+        self.mode = mode;
+        self.main_window.unwrap().set_mode(mode as i32);
+        // Which cells can be selected depends on the mode, so start with an empty selection
+        self.set_selection(vec![], self.focused_cell_index);
+        // The ring of outside clues is shown while editing them
+        self.focused_clue = None;
+        self.update_clue_slots();
+    }
+
+    // Show the outside clues around the board: all slots while editing them, otherwise
+    // only if the board has clues. Clues that are broken are shown in red.
+    fn update_clue_slots(&self) {
+        let size = self.size();
+        let main_window = self.main_window.unwrap();
+        let show_clues = self.layout.is_single() && (!self.clues.is_empty() || self.mode == GameMode::EditClues);
+        let broken_slots = self.constraints.validate(&UiCells(&self.cells)).into_iter()
+            .filter_map(|(_, violation)| match violation {
+                Violation::Clue { clue, .. } => Some(clue.slot(size)),
+                _ => None
+            })
+            .collect::<Vec<_>>();
+        let slot_count = if show_clues { 4 * size } else { 0 };
+        let slots = (0..slot_count).map(|slot| {
+            let (side, line) = Clue::slot_position(slot, size);
+            let text = match self.clues.iter().find(|clue| clue.side == side && clue.line == line) {
+                Some(clue) => clue.label(),
+                None if self.mode == GameMode::EditClues => "·".into(),
+                None => "".into()
+            };
+            ClueSlot {
+                side: (slot / size) as i32,
+                line: line as i32,
+                text: text.into(),
+                is_valid: !broken_slots.contains(&slot),
+                is_focused: self.focused_clue == Some(slot),
+            }
+        }).collect::<Vec<_>>();
+        main_window.set_show_clues(show_clues);
+        main_window.set_clue_slots(ModelHandle::new(Rc::new(VecModel::from(slots))));
+    }
+
+    // Edit clues mode: a click focuses a slot of the ring, clicking the focused slot again
+    // switches its clue between sum, lowest and highest number
+    fn clue_clicked(&mut self, slot: usize) {
+        if self.mode != GameMode::EditClues {
+            return;
+        }
+        if self.focused_clue == Some(slot) {
+            let size = self.size();
+            if let Some(k) = self.clues.iter().position(|clue| clue.slot(size) == slot) {
+                self.push_undo();
+                let clue = &mut self.clues[k];
+                clue.kind = match clue.kind {
+                    ClueKind::Sum => ClueKind::Min,
+                    ClueKind::Min => ClueKind::Max,
+                    ClueKind::Max => ClueKind::Sum,
+                };
+            }
+            self.clues_changed();
+        } else {
+            self.focused_clue = Some(slot);
+            self.update_clue_slots();
+        }
+    }
+
+    // Edit clues mode: digits are appended to the value of the focused clue (a new slot gets
+    // a sum clue), backspace/del removes the clue
+    fn clue_key_pressed(&mut self, slot: usize, e: &KeyEvent) {
+        if self.mode != GameMode::EditClues || self.focused_clue != Some(slot) {
+            return;
+        }
+        let size = self.size();
+        let position = self.clues.iter().position(|clue| clue.slot(size) == slot);
+        let mut chars = e.text.chars();
+        let digit = match (chars.next(), chars.next()) {
+            (Some(c), None) => c.to_digit(10).map(|digit| digit as i32),
+            _ => None
+        };
+        if e.text == "\u{7}" || e.text == "\u{7f}" {
+            if let Some(k) = position {
+                self.push_undo();
+                self.clues.remove(k);
+            }
+        } else if let Some(digit) = digit {
+            self.push_undo();
+            match position {
+                Some(k) => {
+                    let clue = &mut self.clues[k];
+                    // Sums go up to 1 + 2 + ... + size, the lowest/highest numbers up to size
+                    let max_value = if clue.kind == ClueKind::Sum { (size * (size + 1) / 2) as i32 } else { size as i32 };
+                    clue.value = if clue.value * 10 + digit <= max_value { clue.value * 10 + digit } else { digit };
+                },
+                None => {
+                    let (side, line) = Clue::slot_position(slot, size);
+                    self.clues.push(Clue { side, line, kind: ClueKind::Sum, value: digit });
                 }
-                GameMode::EditBlackWhite
-            },
-            "edit-fixed-numbers" => GameMode::EditFixedNumbers,
-            "play-enter-numbers" => GameMode::PlayEnterNumbers,
-            "play-enter-small-numbers" => GameMode::PlayEnterSmallNumbers,
-            "none" => GameMode::None,
-            _ => panic!("Unknown mode: \"{}\"", mode)
+            }
+            // A clue of 0 is no clue
+            self.clues.retain(|clue| clue.value > 0);
+        } else {
+            return;
         }
+        self.clues_changed();
+    }
+
+    fn clues_changed(&mut self) {
+        self.setup_rows_columns();
+        self.validate_board();
+    }
+}
+
+// UI texts in a language
+fn ui_strings(t: &translations::Translation) -> UiStrings {
+    UiStrings {
+        edit: t.edit.into(),
+        play: t.play.into(),
+        new: t.new.into(),
+        save: t.save.into(),
+        load: t.load.into(),
+        solve: t.solve.into(),
+        generate: t.generate.into(),
+        ok: t.ok.into(),
+        cancel: t.cancel.into(),
+        close: t.close.into(),
+        dont_ask_again: t.dont_ask_again.into(),
+        start_playing: t.start_playing.into(),
+        edit_puzzle: t.edit_puzzle.into(),
+        solver_steps: t.solver_steps.into(),
+        boxes: t.boxes.into(),
+        import_image: t.import_image.into(),
+        print: t.print.into(),
+        theme: t.theme.into(),
+        language: t.language.into(),
+        highlights: t.highlights.into(),
+        highlight_peers: t.highlight_peers.into(),
+        highlight_compartment: t.highlight_compartment.into(),
+        highlight_same_value: t.highlight_same_value.into(),
+        gameplay: t.gameplay.into(),
+        auto_remove_small_values: t.auto_remove_small_values.into(),
+        strict_check: t.strict_check.into(),
+        generator: t.generator.into(),
+        generator_black_cells: t.generator_black_cells.into(),
+        board_size: t.board_size.into(),
+        samurai: t.samurai.into(),
+        confirmations: t.confirmations.into(),
+        confirm_restart: t.confirm_restart.into(),
+        confirm_new_board: t.confirm_new_board.into(),
+        confirm_solve: t.confirm_solve.into(),
+        confirm_generate: t.confirm_generate.into(),
+        confirm_load: t.confirm_load.into(),
+        confirm_import_image: t.confirm_import_image.into(),
     }
 }
 
+// Indices of all cells in the rectangle spanned by two cells of a board of the given size
+fn rectangle_indices(size: usize, a: usize, b: usize) -> Vec<usize> {
+    let (rows, columns) = ((a / size).min(b / size)..=(a / size).max(b / size),
+        (a % size).min(b % size)..=(a % size).max(b % size));
+    rows.flat_map(|row| columns.clone().map(move |column| size*row + column)).collect()
+}
+
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
 pub fn main() {
     // This provides better error messages in debug mode.
@@ -293,31 +1765,106 @@ pub fn main() {
     console_error_panic_hook::set_once();
 
     let main_window = MainWindow::new();
+    let settings = Settings::load(&settings::settings_path());
+    main_window.set_languages(ModelHandle::new(Rc::new(VecModel::from(
+        TRANSLATIONS.iter().map(|t| t.name.into()).collect::<Vec<sixtyfps::SharedString>>()))));
     let state = Rc::new(RefCell::new(AppState {
-        cells: Rc::new(sixtyfps::VecModel::<Cell>::from(random_board(P_FIXED, P_WHITE))),
+        cells: Rc::new(sixtyfps::VecModel::<Cell>::from(cells_of_board(&Board::empty_with_layout(settings.layout())))),
         main_window: main_window.as_weak(),
         was_just_solved_timer: Default::default(),
-        editing_cell_index: None,
+        selection: vec![],
+        focused_cell_index: None,
+        drag: None,
+        ctrl_pressed: false,
+        shift_pressed: false,
         rows_columns: vec![],
+        constraints: Constraints::default(),
+        layout: settings.layout(),
+        clues: vec![],
+        focused_clue: None,
+        rules: RuleSet::classic(),
+        phase: Phase::Edit,
         mode: GameMode::None,
+        undo_stack: vec![],
+        redo_stack: vec![],
+        settings,
+        pending_action: None,
+        builtin_themes: builtin_themes(),
+        marking_colors: vec![],
+        theme_watcher: ThemeWatcher::new(settings::config_dir().join("themes")),
+        theme_reload_timer: Default::default(),
+        analysis_job: None,
+        analyzed_puzzle: None,
+        analyzed_solution_count: None,
+        board_job: None,
+        replay: None,
+        job_timer: Default::default(),
     }));
+    // Load user themes, create the theme directory so users can find it
+    let _ = std::fs::create_dir_all(state.borrow().theme_watcher.dir());
+    state.borrow_mut().reload_themes();
+    state.borrow().update_settings_ui();
+    state.borrow_mut().update_marking_colors();
+
+    // Reload user themes when theme files change
+    let state_weak = Rc::downgrade(&state);
+    state.borrow().theme_reload_timer.start(
+        sixtyfps::TimerMode::Repeated,
+        std::time::Duration::from_secs(1),
+        move || {
+            if let Some(state) = state_weak.upgrade() {
+                let has_changed = state.borrow_mut().theme_watcher.has_changed();
+                if has_changed {
+                    state.borrow_mut().reload_themes();
+                }
+            }
+        }
+    );
+
+    // Poll the background jobs (puzzle analysis, solving, generating) for results and progress,
+    // advance the replay of solver steps
+    let state_weak = Rc::downgrade(&state);
+    state.borrow().job_timer.start(
+        sixtyfps::TimerMode::Repeated,
+        TICK,
+        move || {
+            if let Some(state) = state_weak.upgrade() {
+                state.borrow_mut().poll_jobs();
+                state.borrow_mut().advance_replay(TICK);
+            }
+        }
+    );
 
-    // Load a savegame if it exists, otherwise randomize the board
-    if std::path::Path::new(SAVEGAME_PATH).exists() {
-        state.borrow_mut().load_from_file(SAVEGAME_PATH);
-    } 
-    else {
-        state.borrow_mut().set_board(&random_board(P_FIXED, P_WHITE));
+    // Load a savegame if it exists, otherwise (or if it is broken) randomize the board
+    let savegame_path = state.borrow().settings.savegame_path.clone();
+    let has_savegame = std::path::Path::new(&savegame_path).exists();
+    let load_error = if has_savegame { state.borrow_mut().load_from_file(&savegame_path).err() } else { None };
+    if !has_savegame || load_error.is_some() {
+        let options = state.borrow().settings.initial_board.clone();
+        let layout = state.borrow().settings.layout();
+        state.borrow_mut().set_board(&cells_of_board(&random_board(&layout, options.p_fixed, options.p_white)));
     }
     // Setup cells, compute row/column straight structure, validate
     main_window.set_cells(sixtyfps::ModelHandle::new(state.borrow().cells.clone()));
+    state.borrow().update_layout_ui();
     state.borrow_mut().setup_rows_columns();
     state.borrow_mut().validate_board();
+    let phase = state.borrow().loaded_phase();
+    state.borrow_mut().set_phase(phase, GameMode::None);
+    if let Some(error) = load_error {
+        state.borrow().show_load_error(&savegame_path, &error);
+    }
 
     // Handle cell-clicked callback
     let state_copy = state.clone();
     main_window.on_cell_clicked(move |p| {
-        state_copy.borrow_mut().cell_clicked(p as i8);
+        state_copy.borrow_mut().cell_clicked(p as usize);
+    });
+
+    // Handle cell-dragged callback
+    let state_copy = state.clone();
+    main_window.on_cell_dragged(move |p, dx, dy| {
+        state_copy.borrow_mut().cell_dragged(p as usize, dx, dy);
     });
 
     // Handle cell-key-pressed callback
@@ -340,34 +1887,172 @@ pub fn main() {
         }
     });
 
+    // Handle cell-key-released callback
+    let state_copy = state.clone();
+    main_window.on_cell_key_released(move |_, e| {
+        state_copy.borrow_mut().cell_key_released(e);
+    });
+
     // Handle set-mode callback
     let state_copy = state.clone();
     main_window.on_set_mode(move |mode| {
-        state_copy.borrow_mut().set_mode(&mode);
+        match GameMode::from_ui(mode) {
+            Some(mode) => state_copy.borrow_mut().set_mode(mode),
+            None => println!("Ignoring unknown game mode {} from the UI.", mode)
+        }
+    });
+
+    // Handle finish-editing callback
+    let state_copy = state.clone();
+    main_window.on_finish_editing(move || {
+        state_copy.borrow_mut().finish_editing();
+    });
+
+    // Handle edit-puzzle callback
+    let state_copy = state.clone();
+    main_window.on_edit_puzzle(move || {
+        state_copy.borrow_mut().set_phase(Phase::Edit, GameMode::EditFixedNumbers);
     });
 
     // Handle solve-puzzle callback
     let state_copy = state.clone();
     main_window.on_solve_puzzle(move || {
-        state_copy.borrow_mut().solve_puzzle();
+        state_copy.borrow_mut().request_action(PendingAction::Solve);
     });
 
     // Handle save-game callback
     let state_copy = state.clone();
     main_window.on_save_game(move || {
-        state_copy.borrow_mut().save_to_file(SAVEGAME_PATH);
+        let state = state_copy.borrow();
+        let savegame_path = &state.settings.savegame_path;
+        if let Err(error) = state.save_to_file(savegame_path) {
+            state.show_save_error(savegame_path, &error);
+        }
     });
 
-    // Handle generate-puzzle callback (currently deactivated)
+    // Handle generate-puzzle callback: generates a puzzle in the background (after confirmation)
     let state_copy = state.clone();
     main_window.on_generate_puzzle(move || {
-        state_copy.borrow_mut().generate_puzzle();
+        state_copy.borrow_mut().request_action(PendingAction::Generate);
     });
 
-    // Handle reset callback
+    // Handle restart callback
+    let state_copy = state.clone();
+    main_window.on_restart(move || {
+        state_copy.borrow_mut().request_action(PendingAction::Restart);
+    });
+
+    // Handle new-board callback
+    let state_copy = state.clone();
+    main_window.on_new_board(move || {
+        state_copy.borrow_mut().request_action(PendingAction::NewBoard);
+    });
+
+    // Handle load-game callback
+    let state_copy = state.clone();
+    main_window.on_load_game(move || {
+        let savegame_path = state_copy.borrow().settings.savegame_path.clone();
+        if std::path::Path::new(&savegame_path).exists() {
+            state_copy.borrow_mut().request_action(PendingAction::Load);
+        }
+    });
+
+    // Handle print-puzzle callback
+    let state_copy = state.clone();
+    main_window.on_print_puzzle(move || {
+        state_copy.borrow().print_puzzle();
+    });
+
+    // Handle import-image callback
+    let state_copy = state.clone();
+    main_window.on_import_image(move || {
+        state_copy.borrow_mut().request_action(PendingAction::ImportImage);
+    });
+
+    // Handle confirmation-answered callback
+    let state_copy = state.clone();
+    main_window.on_confirmation_answered(move |is_confirmed, dont_ask_again| {
+        state_copy.borrow_mut().confirmation_answered(is_confirmed, dont_ask_again);
+    });
+
+    // Handle settings-changed callback
+    let state_copy = state.clone();
+    main_window.on_settings_changed(move || {
+        state_copy.borrow_mut().settings_changed();
+    });
+
+    // Handle theme-selected callback: show the theme and save it in the settings
+    let state_copy = state.clone();
+    main_window.on_theme_selected(move |index| {
+        let main_window = state_copy.borrow().main_window.unwrap();
+        if let Some(theme) = main_window.get_themes().iter().nth(index as usize) {
+            main_window.set_current_theme(theme);
+            state_copy.borrow_mut().settings_changed();
+        }
+    });
+
+    // Handle marking-clicked callback: colors the selected cells, unless small values are being marked
+    let state_copy = state.clone();
+    main_window.on_marking_clicked(move |marking| {
+        let mark_small_values = state_copy.borrow().main_window.unwrap().get_mark_small_values();
+        state_copy.borrow().update_active_marking_color();
+        if !mark_small_values {
+            state_copy.borrow_mut().mark_cells(marking);
+        }
+    });
+
+    // Handle outside clue callbacks
+    let state_copy = state.clone();
+    main_window.on_clue_clicked(move |slot| {
+        state_copy.borrow_mut().clue_clicked(slot as usize);
+    });
+    let state_copy = state.clone();
+    main_window.on_clue_key_pressed(move |slot, e| {
+        state_copy.borrow_mut().clue_key_pressed(slot as usize, &e);
+    });
+
+    // Handle toggle-boxes callback
+    let state_copy = state.clone();
+    main_window.on_toggle_boxes(move || {
+        state_copy.borrow_mut().toggle_boxes();
+    });
+
+    // Handle replay callbacks
+    let state_copy = state.clone();
+    main_window.on_replay_steps(move || {
+        state_copy.borrow_mut().record_solver_steps();
+    });
+    let state_copy = state.clone();
+    main_window.on_toggle_replay(move || {
+        state_copy.borrow_mut().toggle_replay();
+    });
+    let state_copy = state.clone();
+    main_window.on_step_replay(move || {
+        state_copy.borrow_mut().replay_step();
+    });
+    let state_copy = state.clone();
+    main_window.on_change_replay_speed(move |delta| {
+        state_copy.borrow_mut().change_replay_speed(delta);
+    });
+    let state_copy = state.clone();
+    main_window.on_stop_replay(move || {
+        state_copy.borrow_mut().stop_replay();
+    });
+
+    // Handle cancel-job callback
+    let state_copy = state.clone();
+    main_window.on_cancel_job(move || {
+        state_copy.borrow_mut().cancel_board_job();
+    });
+
+    // Handle undo/redo callbacks
+    let state_copy = state.clone();
+    main_window.on_undo(move || {
+        state_copy.borrow_mut().undo();
+    });
     let state_copy = state.clone();
-    main_window.on_reset(move || {
-        state_copy.borrow_mut().set_board(&empty_board());
+    main_window.on_redo(move || {
+        state_copy.borrow_mut().redo();
     });
 
     main_window.run();
@@ -0,0 +1,135 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use str8ts_core::{Layout, DEFAULT_SIZE, MAX_SIZE, MIN_SIZE};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Directory for user configuration (e.g. ~/.config/sixtyfps-str8ts on Linux),
+// falls back to the current directory if the platform has none
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir().map(|dir| dir.join("sixtyfps-str8ts")).unwrap_or_else(|| PathBuf::from("."))
+}
+
+// Path of the settings file in the configuration directory
+pub fn settings_path() -> PathBuf {
+    config_dir().join("settings.json")
+}
+
+// Which destructive actions ask for confirmation
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Confirmations {
+    pub restart: bool,
+    pub new_board: bool,
+    pub solve: bool,
+    pub generate: bool,
+    pub load: bool,
+    pub import_image: bool,
+}
+
+impl Default for Confirmations {
+    fn default() -> Self {
+        Confirmations { restart: true, new_board: true, solve: true, generate: true, load: true, import_image: true }
+    }
+}
+
+// Which cells are highlighted relative to the focused cell
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Highlights {
+    pub peers: bool,
+    pub compartment: bool,
+    pub same_value: bool,
+}
+
+impl Default for Highlights {
+    fn default() -> Self {
+        Highlights { peers: true, compartment: true, same_value: true }
+    }
+}
+
+// Probabilities for fixed-number cells and white cells of random boards
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BoardOptions {
+    pub p_fixed: f64,
+    pub p_white: f64,
+}
+
+// User settings, stored as JSON. Missing fields take their default values,
+// so settings files from older versions can still be read.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: String, // name of the theme
+    pub language: String, // language code, e.g. "en"
+    pub auto_remove_small_values: bool, // entering a number removes it from the small numbers in row/column
+    pub strict_check: bool, // numbers that break a rule are refused
+    pub highlights: Highlights,
+    pub confirmations: Confirmations,
+    pub initial_board: BoardOptions, // board shown when there is no savegame
+    pub generator: BoardOptions, // starting point of the puzzle generator
+    pub board_size: usize, // size of new, generated and initial boards
+    pub samurai: bool, // new, generated and initial boards are Samurai puzzles instead
+    pub savegame_path: String,
+    pub import_image_path: String, // PNG/JPEG image of a puzzle, read by the Import button
+    pub print_path: String, // PDF written by the Print button
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: "SIMPLE".into(),
+            language: "en".into(),
+            auto_remove_small_values: false,
+            strict_check: false,
+            highlights: Highlights::default(),
+            confirmations: Confirmations::default(),
+            initial_board: BoardOptions { p_fixed: 0.0, p_white: 1.0 },
+            generator: BoardOptions { p_fixed: 0.0, p_white: 0.6 },
+            board_size: DEFAULT_SIZE,
+            samurai: false,
+            savegame_path: "./game_state.json".into(),
+            import_image_path: "./puzzle.png".into(),
+            print_path: "./puzzle.pdf".into(),
+        }
+    }
+}
+
+impl Settings {
+    // Load settings from a JSON file, fall back to the defaults if there is none
+    pub fn load(path: &Path) -> Settings {
+        let mut settings: Settings = match std::fs::read_to_string(path) {
+            Ok(json_data) => serde_json::from_str(&json_data).unwrap_or_else(|e| {
+                println!("Unable to parse settings file {}, using default settings: {}", path.display(), e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default()
+        };
+        settings.board_size = settings.board_size.clamp(MIN_SIZE, MAX_SIZE);
+        settings
+    }
+
+    // Layout of new, generated and initial boards
+    pub fn layout(&self) -> Layout {
+        if self.samurai { Layout::samurai() } else { Layout::single(self.board_size) }
+    }
+
+    // Save settings to a JSON file, creating its directory if necessary
+    pub fn save(&self, path: &Path) {
+        let json_data = serde_json::to_string_pretty(self)
+            .expect("Unable to save settings: unable to create JSON.");
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if let Err(e) = std::fs::write(path, json_data) {
+            println!("Unable to save settings: unable to write file {}: {}", path.display(), e);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::clues::Clue;
+use super::layout::{Layout, MAX_AREA_SIZE};
+use super::rules::RuleSet;
+use super::store::CellStore;
+use std::ops::{Index, IndexMut};
+
+// Supported numbers of cells per row/column. A board of size n uses the digits 1-n.
+pub const MIN_SIZE: usize = 4;
+pub const MAX_SIZE: usize = 12;
+pub const DEFAULT_SIZE: usize = 9;
+
+// Size of a board with the given number of cells, None if there is no such board
+pub fn size_of_cell_count(count: usize) -> Option<usize> {
+    (MIN_SIZE..=MAX_SIZE).find(|size| size * size == count)
+}
+
+// Like size_of_cell_count, but also for the larger areas of boards with several grids
+pub fn area_size_of_cell_count(count: usize) -> Option<usize> {
+    (MIN_SIZE..=MAX_AREA_SIZE).find(|size| size * size == count)
+}
+
+// A digit 1-12. Digits above 9 are written as A-C.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Digit(u8);
+
+impl Digit {
+    // None for values outside 1-12, e.g. -1 for empty cells
+    pub fn new(value: i32) -> Option<Digit> {
+        if (1..=MAX_SIZE as i32).contains(&value) { Some(Digit(value as u8)) } else { None }
+    }
+
+    pub fn get(self) -> i32 {
+        self.0 as i32
+    }
+
+    // All digits of a board of the given size in increasing order
+    pub fn all(size: usize) -> impl Iterator<Item = Digit> {
+        (1..=size as u8).map(Digit)
+    }
+
+    // '1'-'9', then 'A'-'C'
+    pub fn to_char(self) -> char {
+        std::char::from_digit(self.0 as u32, MAX_SIZE as u32 + 1).unwrap().to_ascii_uppercase()
+    }
+
+    pub fn from_char(c: char) -> Option<Digit> {
+        c.to_digit(MAX_SIZE as u32 + 1).and_then(|value| Digit::new(value as i32))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CellKind {
+    White,
+    Black
+}
+
+// A cell of the board: its color, its digit and whether the digit is given by the puzzle
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BoardCell {
+    pub kind: CellKind,
+    pub digit: Option<Digit>,
+    pub is_fixed: bool,
+}
+
+impl BoardCell {
+    pub fn new(kind: CellKind, digit: Option<Digit>, is_fixed: bool) -> BoardCell {
+        BoardCell { kind, digit, is_fixed: is_fixed && digit.is_some() }
+    }
+
+    pub fn is_white(&self) -> bool {
+        self.kind == CellKind::White
+    }
+
+    // The digit as a number, -1 for empty cells (the convention of the UI and the savegames)
+    pub fn value(&self) -> i32 {
+        self.digit.map_or(-1, Digit::get)
+    }
+}
+
+// The cells of a board of size n, the layout of its grids, the rules of its puzzle and its
+// outside clues. Cell i is at pos_x = i % n, pos_y = i / n.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Board {
+    size: usize,
+    cells: Vec<BoardCell>,
+    layout: Layout,
+    rules: RuleSet,
+    clues: Vec<Clue>,
+}
+
+impl Board {
+    // All-white board without digits, with the classic rules
+    pub fn empty(size: usize) -> Board {
+        Board::from_cells(vec![BoardCell::new(CellKind::White, None, false); size * size])
+    }
+
+    // Board of several grids without digits, the grids are white
+    pub fn empty_with_layout(layout: Layout) -> Board {
+        Board::empty(layout.size()).with_layout(layout)
+    }
+
+    // The size is given by the number of cells, which must be the square of a supported size.
+    // The board has a single grid, unless a layout is set.
+    pub fn from_cells(cells: Vec<BoardCell>) -> Board {
+        let size = area_size_of_cell_count(cells.len())
+            .unwrap_or_else(|| panic!("A board can't have {} cells.", cells.len()));
+        Board { size, cells, layout: Layout::single(size), rules: RuleSet::classic(), clues: vec![] }
+    }
+
+    // Unused cells outside the grids become empty black cells
+    pub fn with_layout(mut self, layout: Layout) -> Board {
+        assert_eq!(layout.size(), self.size, "The layout doesn't fit the board.");
+        for i in 0..self.len() {
+            if !layout.is_used(i) {
+                self.cells[i] = BoardCell::new(CellKind::Black, None, false);
+            }
+        }
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_rules(mut self, rules: RuleSet) -> Board {
+        self.rules = rules;
+        self
+    }
+
+    // Clues that don't fit the board are dropped; boards of several grids have none
+    pub fn with_clues(mut self, clues: Vec<Clue>) -> Board {
+        let size = self.size;
+        let is_single = self.layout.is_single();
+        self.clues = clues.into_iter().filter(|clue| is_single && clue.is_valid(size)).collect();
+        self
+    }
+
+    // Copy the cells of any storage
+    pub fn from_store<S: CellStore>(store: &S) -> Board {
+        Board::from_cells(store.iter_cells().collect())
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    // Highest digit: the size of the grids
+    pub fn max_digit(&self) -> usize {
+        self.layout.grid_size
+    }
+
+    pub fn rules(&self) -> RuleSet {
+        self.rules
+    }
+
+    pub fn clues(&self) -> &[Clue] {
+        &self.clues
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn cells(&self) -> &[BoardCell] {
+        &self.cells
+    }
+
+    // The puzzle: black cells and fixed digits, without the digits entered by the player
+    pub fn puzzle(&self) -> Board {
+        let cells = self.cells.iter()
+            .map(|cell| if cell.is_fixed { *cell } else { BoardCell::new(cell.kind, None, false) })
+            .collect();
+        Board { size: self.size, cells, layout: self.layout.clone(), rules: self.rules, clues: self.clues.clone() }
+    }
+
+    // Check if no white cell is empty
+    pub fn is_complete(&self) -> bool {
+        self.cells.iter().all(|cell| !cell.is_white() || cell.digit.is_some())
+    }
+}
+
+impl Index<usize> for Board {
+    type Output = BoardCell;
+
+    fn index(&self, i: usize) -> &BoardCell {
+        &self.cells[i]
+    }
+}
+
+impl IndexMut<usize> for Board {
+    fn index_mut(&mut self, i: usize) -> &mut BoardCell {
+        &mut self.cells[i]
+    }
+}
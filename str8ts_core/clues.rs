@@ -0,0 +1,171 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use serde::{Deserialize, Serialize};
+
+// The edge of the board an outside clue is at
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Top, Side::Bottom, Side::Left, Side::Right];
+
+    pub fn from_name(name: &str) -> Option<Side> {
+        Side::ALL.iter().cloned().find(|side| side.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Side::Top => "top",
+            Side::Bottom => "bottom",
+            Side::Left => "left",
+            Side::Right => "right",
+        }
+    }
+}
+
+// What an outside clue tells about its row/column
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum ClueKind {
+    Sum, // sum of the digits of the first straight seen from the clue
+    Min, // lowest digit in the white cells of the row/column
+    Max, // highest digit in the white cells of the row/column
+}
+
+impl ClueKind {
+    pub const ALL: [ClueKind; 3] = [ClueKind::Sum, ClueKind::Min, ClueKind::Max];
+
+    pub fn from_name(name: &str) -> Option<ClueKind> {
+        ClueKind::ALL.iter().cloned().find(|kind| kind.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClueKind::Sum => "sum",
+            ClueKind::Min => "min",
+            ClueKind::Max => "max",
+        }
+    }
+}
+
+// A clue outside the grid. line counts the rows (left/right) or columns (top/bottom) on
+// screen from 0.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Clue {
+    pub side: Side,
+    pub line: usize,
+    pub kind: ClueKind,
+    pub value: i32,
+}
+
+impl Clue {
+    // Cells of the clue's row/column, starting next to the clue
+    pub fn line_cells(&self, size: usize) -> Vec<usize> {
+        // On screen, row r holds the cells size*c + r and column c the cells size*c..size*(c+1)
+        let cells = (0..size).map(|k| match self.side {
+            Side::Left | Side::Right => size * k + self.line,
+            Side::Top | Side::Bottom => size * self.line + k,
+        });
+        match self.side {
+            Side::Top | Side::Left => cells.collect(),
+            Side::Bottom | Side::Right => cells.rev().collect(),
+        }
+    }
+
+    // The clue as shown next to the board: "17" for a sum, "≥3" for the lowest and "≤8" for
+    // the highest number
+    pub fn label(&self) -> String {
+        match self.kind {
+            ClueKind::Sum => self.value.to_string(),
+            ClueKind::Min => format!("≥{}", self.value),
+            ClueKind::Max => format!("≤{}", self.value),
+        }
+    }
+
+    // Check if the clue fits on a board of the given size
+    pub fn is_valid(&self, size: usize) -> bool {
+        self.line < size && self.value > 0
+    }
+
+    // The clue in the text format: "clue: left 3 sum 17" (lines counted from 1)
+    pub fn to_text(&self) -> String {
+        format!("{} {} {} {}", self.side.name(), self.line + 1, self.kind.name(), self.value)
+    }
+
+    pub fn from_text(text: &str) -> Option<Clue> {
+        match text.split_whitespace().collect::<Vec<_>>()[..] {
+            [side, line, kind, value] => Some(Clue {
+                side: Side::from_name(side)?,
+                line: line.parse::<usize>().ok()?.checked_sub(1)?,
+                kind: ClueKind::from_name(kind)?,
+                value: value.parse().ok()?,
+            }),
+            _ => None
+        }
+    }
+
+    // Index of the clue's slot in the ring around a board: the sides in the order of
+    // Side::ALL, each with one slot per row/column
+    pub fn slot(&self, size: usize) -> usize {
+        Side::ALL.iter().position(|&side| side == self.side).unwrap() * size + self.line
+    }
+
+    pub fn slot_position(slot: usize, size: usize) -> (Side, usize) {
+        (Side::ALL[slot / size], slot % size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let clue = Clue::from_text(" left 3 sum 17 ").unwrap();
+        assert_eq!(clue, Clue { side: Side::Left, line: 2, kind: ClueKind::Sum, value: 17 });
+        assert_eq!(clue.to_text(), "left 3 sum 17");
+        for text in &["left 0 sum 3", "middle 1 sum 3", "top 1 average 3", "top 1 min", "top 1 min x"] {
+            assert!(Clue::from_text(text).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn labels() {
+        let clue = |kind| Clue { side: Side::Top, line: 0, kind, value: 4 };
+        assert_eq!(clue(ClueKind::Sum).label(), "4");
+        assert_eq!(clue(ClueKind::Min).label(), "≥4");
+        assert_eq!(clue(ClueKind::Max).label(), "≤4");
+    }
+
+    #[test]
+    fn line_cells_start_next_to_the_clue() {
+        let clue = |side| Clue { side, line: 1, kind: ClueKind::Sum, value: 4 };
+        assert_eq!(clue(Side::Left).line_cells(4), vec![1, 5, 9, 13]);
+        assert_eq!(clue(Side::Right).line_cells(4), vec![13, 9, 5, 1]);
+        assert_eq!(clue(Side::Top).line_cells(4), vec![4, 5, 6, 7]);
+        assert_eq!(clue(Side::Bottom).line_cells(4), vec![7, 6, 5, 4]);
+        assert!(clue(Side::Top).is_valid(4));
+        assert!(!Clue { line: 4, ..clue(Side::Top) }.is_valid(4));
+    }
+
+    #[test]
+    fn slots() {
+        for &side in &Side::ALL {
+            for line in 0..6 {
+                let clue = Clue { side, line, kind: ClueKind::Min, value: 1 };
+                assert_eq!(Clue::slot_position(clue.slot(6), 6), (side, line));
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{Board, BoardCell, Digit};
+use super::clues::{Clue, ClueKind, Side};
+use super::formats::{format_text, parse_text, puzzle_id};
+use super::layout::{GridPosition, Layout};
+use super::rules::box_shape;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// A puzzle of a collection and what is known about it. The puzzle and its solution are in
+// the text format. Empty fields are left out of the file.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CollectionEntry {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub author: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub difficulty: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source: String,
+    pub puzzle: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<String>,
+}
+
+impl CollectionEntry {
+    // Entry of the puzzle of a board (without the player's digits), without metadata
+    pub fn new(board: &Board) -> CollectionEntry {
+        let puzzle = board.puzzle();
+        CollectionEntry {
+            id: puzzle_id(&puzzle),
+            title: String::new(),
+            author: String::new(),
+            difficulty: String::new(),
+            source: String::new(),
+            puzzle: format_text(&puzzle),
+            solution: None,
+        }
+    }
+
+    pub fn with_solution(mut self, solution: &Board) -> CollectionEntry {
+        self.solution = Some(format_text(solution));
+        self
+    }
+
+    pub fn board(&self) -> Result<Board, String> {
+        parse_text(&self.puzzle)
+    }
+
+    pub fn solution(&self) -> Result<Option<Board>, String> {
+        self.solution.as_deref().map(parse_text).transpose()
+    }
+}
+
+// Collection files hold one entry per line as a JSON object, so entries can be appended
+// without rewriting the file. Empty lines are skipped.
+pub fn iter_collection(data: &str) -> impl Iterator<Item = Result<CollectionEntry, String>> + '_ {
+    data.lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(k, line)| parse_entry(line).map_err(|error| format!("line {}: {}", k + 1, error)))
+}
+
+pub fn parse_entry(line: &str) -> Result<CollectionEntry, String> {
+    let entry: CollectionEntry = serde_json::from_str(line).map_err(|_| "unable to parse JSON.".to_string())?;
+    entry.board()?;
+    entry.solution()?;
+    Ok(entry)
+}
+
+// The line of an entry, ending with a newline
+pub fn format_entry(entry: &CollectionEntry) -> String {
+    serde_json::to_string(entry).expect("Unable to create JSON.") + "\n"
+}
+
+// The entries of a collection file. Puzzles count as the same if one is a rotation or
+// reflection of the other, possibly with its digits inverted (see canonical_form).
+#[derive(Default)]
+pub struct Collection {
+    entries: Vec<CollectionEntry>,
+    puzzles: HashSet<String>, // canonical forms of the entries
+}
+
+impl Collection {
+    pub fn new() -> Collection {
+        Collection::default()
+    }
+
+    // Read all entries, keeping duplicates
+    pub fn parse(data: &str) -> Result<Collection, String> {
+        let mut collection = Collection::new();
+        for entry in iter_collection(data) {
+            let entry = entry?;
+            collection.puzzles.insert(canonical_form(&entry.board()?));
+            collection.entries.push(entry);
+        }
+        Ok(collection)
+    }
+
+    pub fn entries(&self) -> &[CollectionEntry] {
+        &self.entries
+    }
+
+    pub fn contains(&self, board: &Board) -> bool {
+        self.puzzles.contains(&canonical_form(board))
+    }
+
+    // Add an entry unless the collection has its puzzle already, returns whether it was added
+    pub fn append(&mut self, entry: CollectionEntry) -> Result<bool, String> {
+        if !self.puzzles.insert(canonical_form(&entry.board()?)) {
+            return Ok(false);
+        }
+        self.entries.push(entry);
+        Ok(true)
+    }
+
+    // Keep the first entry of each puzzle, returns the removed entries
+    pub fn remove_duplicates(&mut self) -> Vec<CollectionEntry> {
+        let mut puzzles = HashSet::new();
+        let (entries, duplicates) = self.entries.drain(..)
+            .partition(|entry| entry.board().map_or(true, |board| puzzles.insert(canonical_form(&board))));
+        self.entries = entries;
+        duplicates
+    }
+
+    // The collection file
+    pub fn to_text(&self) -> String {
+        self.entries.iter().map(format_entry).collect()
+    }
+}
+
+// The same text for puzzles that are rotations or reflections of each other, or that have
+// their digits inverted (d becomes max + 1 - d, which keeps straights straight): the smallest
+// text format of all these variants of the puzzle
+pub fn canonical_form(board: &Board) -> String {
+    variants(&board.puzzle()).iter().map(format_text).min().expect("A board is its own variant.")
+}
+
+// The boards the eight symmetries of the square map a board to, with and without inverted
+// digits. Boxes of more columns than rows (e.g. 2×3) would change shape when rows and columns
+// are swapped, so these boards only get the flips.
+fn variants(board: &Board) -> Vec<Board> {
+    let grid_size = board.max_digit();
+    let has_oblong_boxes = matches!(box_shape(grid_size), Some((rows, columns)) if rows != columns);
+    let transposes = if board.rules().has_boxes(grid_size) && has_oblong_boxes { vec![false] } else { vec![false, true] };
+    let mut variants = vec![];
+    for &transpose in &transposes {
+        for &flip_rows in &[false, true] {
+            for &flip_columns in &[false, true] {
+                for &invert in &[false, true] {
+                    variants.push(transform(board, transpose, flip_rows, flip_columns, invert));
+                }
+            }
+        }
+    }
+    variants
+}
+
+// Swap rows and columns on screen, then reverse the order of the rows and/or the columns
+fn transform(board: &Board, transpose: bool, flip_rows: bool, flip_columns: bool, invert: bool) -> Board {
+    let n = board.size();
+    let max = board.max_digit() as i32;
+    let position = |row: usize, column: usize, span: usize| {
+        let (row, column) = if transpose { (column, row) } else { (row, column) };
+        (if flip_rows { n - span - row } else { row }, if flip_columns { n - span - column } else { column })
+    };
+    let invert_digit = |digit: Digit| if invert { Digit::new(max + 1 - digit.get()).unwrap() } else { digit };
+
+    // On screen, cell i is in row i % n and column i / n
+    let mut cells = board.cells().to_vec();
+    for (i, cell) in board.cells().iter().enumerate() {
+        let (row, column) = position(i % n, i / n, 1);
+        cells[n * column + row] = BoardCell::new(cell.kind, cell.digit.map(invert_digit), cell.is_fixed);
+    }
+
+    let layout = board.layout();
+    let mut grids = layout.grids.iter()
+        .map(|grid| {
+            let (row, column) = position(grid.row, grid.column, layout.grid_size);
+            GridPosition { row, column }
+        })
+        .collect::<Vec<_>>();
+    grids.sort_by_key(|grid| (grid.row, grid.column));
+
+    let mut clues = board.clues().iter().map(|clue| transform_clue(board, clue, transpose, flip_rows, flip_columns, invert))
+        .collect::<Vec<_>>();
+    clues.sort_by_key(|clue| (clue.slot(n), clue.kind.name(), clue.value));
+
+    Board::from_cells(cells)
+        .with_layout(Layout { grid_size: layout.grid_size, grids })
+        .with_rules(board.rules())
+        .with_clues(clues)
+}
+
+// Left/right clues count rows and top/bottom clues columns, so a clue moves with its row or column
+fn transform_clue(board: &Board, clue: &Clue, transpose: bool, flip_rows: bool, flip_columns: bool, invert: bool) -> Clue {
+    let n = board.size();
+    let max = board.max_digit() as i32;
+    let (mut side, mut line) = (clue.side, clue.line);
+    if transpose {
+        side = match side { Side::Left => Side::Top, Side::Top => Side::Left, Side::Right => Side::Bottom, Side::Bottom => Side::Right };
+    }
+    if flip_rows {
+        match side {
+            Side::Left | Side::Right => line = n - 1 - line,
+            Side::Top => side = Side::Bottom,
+            Side::Bottom => side = Side::Top,
+        }
+    }
+    if flip_columns {
+        match side {
+            Side::Top | Side::Bottom => line = n - 1 - line,
+            Side::Left => side = Side::Right,
+            Side::Right => side = Side::Left,
+        }
+    }
+    // Inverting the digits of k cells turns their sum s into k * (max + 1) - s, and swaps the
+    // lowest and the highest digit
+    let (kind, value) = match clue.kind {
+        _ if !invert => (clue.kind, clue.value),
+        ClueKind::Sum => {
+            let straight = clue.line_cells(n).into_iter()
+                .skip_while(|&i| !board[i].is_white())
+                .take_while(|&i| board[i].is_white())
+                .count() as i32;
+            (ClueKind::Sum, straight * (max + 1) - clue.value)
+        },
+        ClueKind::Min => (ClueKind::Max, max + 1 - clue.value),
+        ClueKind::Max => (ClueKind::Min, max + 1 - clue.value),
+    };
+    Clue { side, line, kind, value }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::RuleSet;
+
+    const PUZZLE: &str = "clue: left 1 sum 15\nc...a.\n.2.##.\n#5.##.\n#..#3.\n...##6\n.6#1.#\n";
+
+    #[test]
+    fn variants_are_duplicates() {
+        let board = parse_text(PUZZLE).unwrap();
+        let variants = variants(&board);
+        // Eight symmetries of the square, each with and without inverted digits
+        assert_eq!(variants.len(), 16);
+        assert_eq!(variants.iter().map(format_text).collect::<HashSet<_>>().len(), 16);
+        let canonical = canonical_form(&board);
+        assert!(variants.iter().all(|variant| canonical_form(variant) == canonical));
+
+        // The inverted puzzle has the inverted clue: a straight of 3 cells adds up to 3 * 7 - 15
+        let inverted = transform(&board, false, false, false, true);
+        assert_eq!(inverted.clues(), &[Clue { side: Side::Left, line: 0, kind: ClueKind::Sum, value: 6 }]);
+        assert_eq!(inverted[0].digit, Digit::new(4));
+    }
+
+    #[test]
+    fn other_puzzles_are_no_duplicates() {
+        let board = parse_text(PUZZLE).unwrap();
+        let mut other = board.clone();
+        other[1] = BoardCell::new(other[1].kind, Digit::new(4), true);
+        assert_ne!(canonical_form(&other), canonical_form(&board));
+        assert_ne!(canonical_form(&board.clone().with_clues(vec![])), canonical_form(&board));
+    }
+
+    #[test]
+    fn oblong_boxes_are_not_transposed() {
+        let board = parse_text("rules: sudoku\n....5#\n#..1..\n.....3\n.#..##\n..#...\n..###.\n").unwrap();
+        assert_eq!(variants(&board).len(), 8);
+        assert_eq!(variants(&board.with_rules(RuleSet::classic())).len(), 16);
+    }
+
+    #[test]
+    fn dedup() {
+        let board = parse_text(PUZZLE).unwrap();
+        let mut collection = Collection::new();
+        assert!(collection.append(CollectionEntry::new(&board)).unwrap());
+        for variant in variants(&board) {
+            assert!(collection.contains(&variant));
+            assert!(!collection.append(CollectionEntry::new(&variant)).unwrap());
+        }
+        assert_eq!(collection.entries().len(), 1);
+
+        // Parsing keeps duplicates, removing them keeps the first entry of each puzzle
+        let data = variants(&board).iter().map(|variant| format_entry(&CollectionEntry::new(variant))).collect::<String>();
+        let mut collection = Collection::parse(&data).unwrap();
+        assert_eq!(collection.entries().len(), 16);
+        assert_eq!(collection.remove_duplicates().len(), 15);
+        assert_eq!(collection.entries()[0].puzzle, format_text(&board));
+    }
+
+    #[test]
+    fn collection_file_round_trip() {
+        let board = parse_text(PUZZLE).unwrap();
+        let mut entry = CollectionEntry::new(&board);
+        entry.title = "Test".into();
+        let line = format_entry(&entry);
+        assert!(!line.contains("author"));
+        assert_eq!(parse_entry(line.trim()).unwrap(), entry);
+        let collection = Collection::parse(&format!("\n{}\n", line)).unwrap();
+        assert_eq!(collection.to_text(), line);
+        assert!(Collection::parse("{\"id\": \"x\", \"puzzle\": \"...\"}").is_err());
+    }
+}
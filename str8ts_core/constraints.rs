@@ -0,0 +1,492 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{Board, Digit, MAX_SIZE};
+use super::clues::{Clue, ClueKind, Side};
+use super::row::Row;
+use super::solver::compute_rows_columns;
+use super::store::CellStore;
+
+// A broken rule
+#[derive(Clone)]
+pub enum Violation {
+    // A digit occurs in several cells
+    Duplicate { digit: Digit, cells: Vec<usize> },
+    // The digits of a straight are too far apart: max - min must be less than the straight's length
+    Straight { cells: Vec<usize>, digits: Vec<Digit>, min: Digit, max: Digit },
+    // The digits of the cells an outside clue is about don't match it
+    Clue { clue: Clue, cells: Vec<usize>, digits: Vec<Digit> },
+}
+
+impl Violation {
+    // The cells that break the rule (for straights: all cells of the straight)
+    pub fn cells(&self) -> &Vec<usize> {
+        match self {
+            Violation::Duplicate { cells, .. } | Violation::Straight { cells, .. } | Violation::Clue { cells, .. } => cells
+        }
+    }
+}
+
+// The part of the board a constraint is about, as seen on screen (counted from 0)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Unit {
+    Row(usize),
+    Column(usize),
+    Box(usize),
+}
+
+// A broken rule in words: the id of a text of the translations (see Translation::explanation)
+// and the values of its {placeholders}
+pub struct Explanation {
+    pub unit: Unit,
+    pub text: &'static str,
+    pub args: Vec<(&'static str, String)>,
+}
+
+// A rule of the puzzle. Validating a board, the candidates of the solver and the
+// explanations of broken rules all go through the constraints.
+pub trait Constraint {
+    // Cells the constraint is about; prune is only called for these
+    fn cells(&self) -> &[usize];
+
+    // Find the broken rules, returns no violations if the board keeps the rule
+    fn validate(&self, board: &dyn CellStore) -> Vec<Violation>;
+
+    // Remove the candidates of a cell that would break the rule
+    fn prune(&self, cell_index: usize, candidates: &mut Vec<Digit>, board: &dyn CellStore);
+
+    // Describe a violation found by validate
+    fn explain(&self, violation: &Violation) -> Explanation;
+}
+
+// No digit is repeated in a row, column or box
+pub struct UniqueDigits {
+    unit: Unit,
+    cells: Vec<usize>,
+}
+
+impl UniqueDigits {
+    pub fn new(unit: Unit, cells: Vec<usize>) -> UniqueDigits {
+        UniqueDigits { unit, cells }
+    }
+}
+
+impl Constraint for UniqueDigits {
+    fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+
+    fn validate(&self, board: &dyn CellStore) -> Vec<Violation> {
+        // Per digit, store the indices of cells it occurs in
+        let mut occurrences = vec![vec![]; MAX_SIZE];
+        for &i in &self.cells {
+            if let Some(digit) = board.digit(i) {
+                occurrences[(digit.get() - 1) as usize].push(i);
+            }
+        }
+        // Find the digits with multiple occurences
+        Digit::all(MAX_SIZE).zip(occurrences)
+            .filter(|(_, cells)| cells.len() > 1)
+            .map(|(digit, cells)| Violation::Duplicate { digit, cells })
+            .collect()
+    }
+
+    // Keep the digits not yet present in the unit
+    fn prune(&self, _cell_index: usize, candidates: &mut Vec<Digit>, board: &dyn CellStore) {
+        let present = self.cells.iter().filter_map(|&i| board.digit(i)).collect::<Vec<_>>();
+        candidates.retain(|digit| !present.contains(digit));
+    }
+
+    fn explain(&self, violation: &Violation) -> Explanation {
+        let args = match violation {
+            Violation::Duplicate { digit, cells } =>
+                vec![("digit", digit.to_char().to_string()), ("count", cells.len().to_string())],
+            _ => vec![]
+        };
+        Explanation { unit: self.unit, text: "duplicate", args }
+    }
+}
+
+// The digits of each straight (compartment of white cells) of a row or column form a
+// sequence without gaps
+pub struct Straights {
+    unit: Unit,
+    cells: Vec<usize>,
+    straights: Vec<Vec<usize>>,
+}
+
+impl Straights {
+    pub fn new(unit: Unit, row: &Row) -> Straights {
+        Straights { unit, cells: row.cells().clone(), straights: row.straights().clone() }
+    }
+
+    fn straight_of(&self, cell_index: usize) -> Option<&Vec<usize>> {
+        self.straights.iter().find(|s| s.contains(&cell_index))
+    }
+}
+
+impl Constraint for Straights {
+    fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+
+    // Find straights where the min and max digit are too far apart
+    fn validate(&self, board: &dyn CellStore) -> Vec<Violation> {
+        self.straights.iter()
+            .map(|straight| (straight, straight.iter().filter_map(|&i| board.digit(i)).collect::<Vec<_>>()))
+            .filter_map(|(straight, digits)| {
+                let (&min, &max) = (digits.iter().min()?, digits.iter().max()?);
+                if (max.get() - min.get()) as usize >= straight.len() {
+                    Some(Violation::Straight { cells: straight.clone(), digits, min, max })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // Keep the candidates that would not extend the cell's straight too far
+    fn prune(&self, cell_index: usize, candidates: &mut Vec<Digit>, board: &dyn CellStore) {
+        // Black cells are in no straight
+        let straight_indices = match self.straight_of(cell_index) {
+            Some(straight) => straight,
+            None => return
+        };
+        // Get the digits of non-empty cells in the straight
+        let straight = straight_indices.iter()
+            .filter_map(|&i| board.digit(i)).map(Digit::get).collect::<Vec<_>>();
+
+        // An empty straight (i.e. without min/max) allows all candidates
+        if let (Some(&min), Some(&max)) = (straight.iter().min(), straight.iter().max()) {
+            let len = straight_indices.len() as i32;
+            candidates.retain(|digit| {
+                let val = digit.get();
+                (min < val && val < max)
+                || (val < min && max - val < len)
+                || (max < val && val - min < len)
+            });
+        }
+    }
+
+    fn explain(&self, violation: &Violation) -> Explanation {
+        let args = match violation {
+            Violation::Straight { cells, digits, min, max } => vec![
+                ("digits", digits.iter().map(|digit| digit.to_char().to_string()).collect::<Vec<_>>().join(", ")),
+                ("min", min.to_char().to_string()),
+                ("max", max.to_char().to_string()),
+                ("span", (max.get() - min.get() + 1).to_string()),
+                ("length", cells.len().to_string()),
+            ],
+            _ => vec![]
+        };
+        Explanation { unit: self.unit, text: "straight", args }
+    }
+}
+
+// An outside clue about the sum of the first straight or the lowest/highest digit of a row/column
+pub struct OutsideClue {
+    clue: Clue,
+    cells: Vec<usize>,
+}
+
+impl OutsideClue {
+    pub fn new(clue: Clue, board: &dyn CellStore) -> OutsideClue {
+        let line = clue.line_cells(board.size()).into_iter();
+        let cells = match clue.kind {
+            // The first straight: skip the black cells next to the clue, stop at the next black cell
+            ClueKind::Sum => line.skip_while(|&i| !board.is_white(i)).take_while(|&i| board.is_white(i)).collect(),
+            ClueKind::Min | ClueKind::Max => line.filter(|&i| board.is_white(i)).collect(),
+        };
+        OutsideClue { clue, cells }
+    }
+}
+
+impl Constraint for OutsideClue {
+    fn cells(&self) -> &[usize] {
+        &self.cells
+    }
+
+    // Broken if the digits can't match the clue anymore, or don't match it once all cells are filled
+    fn validate(&self, board: &dyn CellStore) -> Vec<Violation> {
+        let digits = self.cells.iter().filter_map(|&i| board.digit(i)).collect::<Vec<_>>();
+        let is_complete = digits.len() == self.cells.len();
+        let value = self.clue.value;
+        let is_broken = match self.clue.kind {
+            ClueKind::Sum => {
+                let sum = digits.iter().map(|digit| digit.get()).sum::<i32>();
+                sum > value || (is_complete && sum != value)
+            },
+            ClueKind::Min => digits.iter().any(|digit| digit.get() < value)
+                || (is_complete && !digits.iter().any(|digit| digit.get() == value)),
+            ClueKind::Max => digits.iter().any(|digit| digit.get() > value)
+                || (is_complete && !digits.iter().any(|digit| digit.get() == value)),
+        };
+        if is_broken {
+            vec![Violation::Clue { clue: self.clue, cells: self.cells.clone(), digits }]
+        } else {
+            vec![]
+        }
+    }
+
+    fn prune(&self, cell_index: usize, candidates: &mut Vec<Digit>, board: &dyn CellStore) {
+        let others = self.cells.iter().filter(|&&i| i != cell_index);
+        let present = others.clone().filter_map(|&i| board.digit(i)).collect::<Vec<_>>();
+        // Number of other cells that are still empty
+        let empty = others.count() - present.len();
+        let value = self.clue.value;
+        match self.clue.kind {
+            // Keep the digits that leave a sum the empty cells can still reach with different digits
+            ClueKind::Sum => {
+                let rest = value - present.iter().map(|digit| digit.get()).sum::<i32>();
+                candidates.retain(|&digit| {
+                    let available = Digit::all(board.size())
+                        .filter(|other| *other != digit && !present.contains(other))
+                        .map(Digit::get)
+                        .collect::<Vec<_>>();
+                    let lowest = available.iter().take(empty).sum::<i32>();
+                    let highest = available.iter().rev().take(empty).sum::<i32>();
+                    available.len() >= empty && lowest <= rest - digit.get() && rest - digit.get() <= highest
+                });
+            },
+            // Keep the digits on the right side of the clue. The last empty cell must take the
+            // clue's digit if no other cell has it.
+            ClueKind::Min | ClueKind::Max => {
+                let has_value = present.iter().any(|digit| digit.get() == value);
+                candidates.retain(|digit| {
+                    let is_allowed = if self.clue.kind == ClueKind::Min { digit.get() >= value } else { digit.get() <= value };
+                    is_allowed && (empty > 0 || has_value || digit.get() == value)
+                });
+            },
+        }
+    }
+
+    fn explain(&self, violation: &Violation) -> Explanation {
+        let unit = match self.clue.side {
+            Side::Left | Side::Right => Unit::Row(self.clue.line),
+            Side::Top | Side::Bottom => Unit::Column(self.clue.line),
+        };
+        let sum = match violation {
+            Violation::Clue { digits, .. } => digits.iter().map(|digit| digit.get()).sum::<i32>(),
+            _ => 0
+        };
+        let (text, args) = match self.clue.kind {
+            ClueKind::Sum => ("clue_sum", vec![("value", self.clue.value.to_string()), ("sum", sum.to_string())]),
+            ClueKind::Min => ("clue_min", vec![("value", self.clue.value.to_string())]),
+            ClueKind::Max => ("clue_max", vec![("value", self.clue.value.to_string())]),
+        };
+        Explanation { unit, text, args }
+    }
+}
+
+// A part of the rules that can be switched on and off: the constraints it adds to a board
+pub struct Variant {
+    pub name: &'static str,
+    pub is_enabled: fn(&Board) -> bool,
+    pub build: fn(&Board) -> Vec<Box<dyn Constraint>>,
+}
+
+// All variants. A new variant adds its constraints here and a switch to RuleSet.
+pub static VARIANTS: [Variant; 4] = [
+    Variant { name: "unique", is_enabled: always, build: unique_rows_columns },
+    Variant { name: "straights", is_enabled: always, build: straights },
+    Variant { name: "boxes", is_enabled: has_boxes, build: unique_boxes },
+    Variant { name: "clues", is_enabled: always, build: outside_clues },
+];
+
+fn always(_board: &Board) -> bool {
+    true
+}
+
+// Boxes are only supported on boards of a single grid
+fn has_boxes(board: &Board) -> bool {
+    board.layout().is_single() && board.rules().has_boxes(board.size())
+}
+
+// The rows and columns of all grids; rows shared by several grids appear once per grid
+fn rows_columns(board: &Board) -> Vec<Row> {
+    compute_rows_columns(board, board.layout(), board.rules()).into_iter()
+        .filter(|row| !matches!(row.unit(), Unit::Box(_)))
+        .collect()
+}
+
+fn unique_rows_columns(board: &Board) -> Vec<Box<dyn Constraint>> {
+    rows_columns(board).into_iter()
+        .map(|row| Box::new(UniqueDigits::new(row.unit(), row.cells().clone())) as Box<dyn Constraint>)
+        .collect()
+}
+
+fn straights(board: &Board) -> Vec<Box<dyn Constraint>> {
+    rows_columns(board).iter()
+        .map(|row| Box::new(Straights::new(row.unit(), row)) as Box<dyn Constraint>)
+        .collect()
+}
+
+fn unique_boxes(board: &Board) -> Vec<Box<dyn Constraint>> {
+    compute_rows_columns(board, board.layout(), board.rules()).into_iter()
+        .filter(|row| matches!(row.unit(), Unit::Box(_)))
+        .map(|row| Box::new(UniqueDigits::new(row.unit(), row.cells().clone())) as Box<dyn Constraint>)
+        .collect()
+}
+
+// Boards without clues have none of these constraints
+fn outside_clues(board: &Board) -> Vec<Box<dyn Constraint>> {
+    board.clues().iter().map(|&clue| Box::new(OutsideClue::new(clue, board)) as Box<dyn Constraint>).collect()
+}
+
+// The constraints of a board under its rules, i.e. of all enabled variants. Like the
+// rows/columns, they depend on the black and white cells and are rebuilt when these change.
+#[derive(Default)]
+pub struct Constraints {
+    constraints: Vec<Box<dyn Constraint>>,
+    cell_constraints: Vec<Vec<usize>>, // per cell: indices of the constraints about it
+    max_digit: usize,
+}
+
+impl Constraints {
+    pub fn new(board: &Board) -> Constraints {
+        let constraints = VARIANTS.iter()
+            .filter(|variant| (variant.is_enabled)(board))
+            .flat_map(|variant| (variant.build)(board))
+            .collect::<Vec<_>>();
+        let mut cell_constraints = vec![vec![]; board.len()];
+        for (k, constraint) in constraints.iter().enumerate() {
+            for &i in constraint.cells() {
+                cell_constraints[i].push(k);
+            }
+        }
+        Constraints { constraints, cell_constraints, max_digit: board.max_digit() }
+    }
+
+    // Highest digit of the board, i.e. the size of its grids
+    pub fn max_digit(&self) -> usize {
+        self.max_digit
+    }
+
+    pub fn len(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constraints.is_empty()
+    }
+
+    // All broken rules, with the index of the constraint that found them
+    pub fn validate(&self, board: &dyn CellStore) -> Vec<(usize, Violation)> {
+        self.constraints.iter().enumerate()
+            .flat_map(|(k, constraint)| constraint.validate(board).into_iter().map(move |violation| (k, violation)))
+            .collect()
+    }
+
+    // Currently possible digits in a cell that break none of the rules
+    pub fn candidates(&self, cell_index: usize, board: &dyn CellStore) -> Vec<Digit> {
+        let mut candidates = Digit::all(self.max_digit).collect::<Vec<_>>();
+        for &k in &self.cell_constraints[cell_index] {
+            self.constraints[k].prune(cell_index, &mut candidates, board);
+        }
+        candidates
+    }
+
+    // Describe a violation found by the k-th constraint
+    pub fn explain(&self, k: usize, violation: &Violation) -> Explanation {
+        self.constraints[k].explain(violation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::parse_text;
+    use crate::layout::Layout;
+    use crate::rules::RuleSet;
+
+    fn violations(text: &str) -> Vec<(Unit, &'static str)> {
+        let board = parse_text(text).unwrap();
+        let constraints = Constraints::new(&board);
+        constraints.validate(&board).iter()
+            .map(|(k, violation)| constraints.explain(*k, violation))
+            .map(|explanation| (explanation.unit, explanation.text))
+            .collect()
+    }
+
+    #[test]
+    fn duplicates() {
+        assert_eq!(violations("1..1\n....\n....\n....\n"), vec![(Unit::Row(0), "duplicate")]);
+        assert_eq!(violations("1...\n1...\n....\n....\n"), vec![(Unit::Column(0), "duplicate")]);
+        // Black cells count too
+        assert_eq!(violations("a..1\n....\n....\n....\n"), vec![(Unit::Row(0), "duplicate")]);
+        assert!(violations("1234\n2341\n3412\n4123\n").is_empty());
+    }
+
+    #[test]
+    fn straights() {
+        assert_eq!(violations("1.4#\n....\n....\n....\n"), vec![(Unit::Row(0), "straight")]);
+        assert!(violations("1.3#\n....\n....\n....\n").is_empty());
+        // The black cell splits the row into two straights
+        assert_eq!(violations("1#3.\n....\n....\n....\n"), vec![]);
+        assert_eq!(violations("14#.\n....\n....\n....\n"), vec![(Unit::Row(0), "straight")]);
+    }
+
+    #[test]
+    fn candidates() {
+        let board = parse_text("1.#.\n..3.\n....\n....\n").unwrap();
+        let constraints = Constraints::new(&board);
+        // On screen, row r and column c hold the cell 4 * c + r
+        assert_eq!(constraints.candidates(4, &board), vec![Digit::new(2).unwrap()]);
+        assert_eq!(constraints.candidates(1, &board), vec![Digit::new(2).unwrap(), Digit::new(4).unwrap()]);
+    }
+
+    #[test]
+    fn boxes_only_with_sudoku_rules() {
+        assert!(violations("1...\n.1..\n....\n....\n").is_empty());
+        assert_eq!(violations("rules: sudoku\n1...\n.1..\n....\n....\n"), vec![(Unit::Box(0), "duplicate")]);
+
+        // 5×5 boards have no boxes
+        let board = parse_text("rules: sudoku\n.....\n.....\n.....\n.....\n.....\n").unwrap();
+        assert_eq!(Constraints::new(&board).len(), Constraints::new(&board.clone().with_rules(RuleSet::classic())).len());
+    }
+
+    #[test]
+    fn samurai_cells_follow_every_grid() {
+        let layout = Layout::samurai();
+        let n = layout.size();
+        let constraints = Constraints::new(&Board::empty_with_layout(layout));
+        // Unique digits and straights of a row and a column, per grid of the cell
+        assert_eq!(constraints.cell_constraints[n * 10 + 10].len(), 4);
+        assert_eq!(constraints.cell_constraints[n * 7 + 7].len(), 8);
+        assert!(constraints.cell_constraints[n * 3 + 10].is_empty());
+    }
+
+    #[test]
+    fn sum_clues() {
+        assert!(violations("clue: left 1 sum 3\n12#.\n....\n....\n....\n").is_empty());
+        assert!(violations("clue: left 1 sum 3\n1.#.\n....\n....\n....\n").is_empty());
+        assert_eq!(violations("clue: left 1 sum 3\n23#.\n....\n....\n....\n"), vec![(Unit::Row(0), "clue_sum")]);
+        // Seen from the right, the first straight is the last cell
+        assert!(violations("clue: right 1 sum 3\n12#3\n....\n....\n....\n").is_empty());
+        // Black cells next to the clue are skipped
+        assert!(violations("clue: top 1 sum 7\n#...\n3...\n4...\n#...\n").is_empty());
+
+        let board = parse_text("clue: left 1 sum 3\n1.#.\n....\n....\n....\n").unwrap();
+        assert_eq!(Constraints::new(&board).candidates(4, &board), vec![Digit::new(2).unwrap()]);
+    }
+
+    #[test]
+    fn min_max_clues() {
+        assert!(violations("clue: left 1 min 2\n2.#.\n....\n....\n....\n").is_empty());
+        assert_eq!(violations("clue: left 1 min 2\n1.#.\n....\n....\n....\n"), vec![(Unit::Row(0), "clue_min")]);
+        // Once the row is full, the clue's digit must be in it
+        assert_eq!(violations("clue: left 1 min 2\n34#4\n....\n....\n....\n").len(), 2);
+        assert_eq!(violations("clue: bottom 2 max 3\n....\n.3..\n.2..\n.#..\n"), vec![]);
+        assert_eq!(violations("clue: bottom 2 max 3\n....\n.4..\n.3..\n.#..\n"), vec![(Unit::Column(1), "clue_max")]);
+
+        let board = parse_text("clue: left 1 min 2\n3.#.\n....\n....\n....\n").unwrap();
+        let constraints = Constraints::new(&board);
+        assert_eq!(constraints.candidates(4, &board), vec![Digit::new(2).unwrap(), Digit::new(4).unwrap()]);
+    }
+}
@@ -0,0 +1,354 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{size_of_cell_count, Board, BoardCell, CellKind, Digit, MAX_SIZE, MIN_SIZE};
+use super::clues::Clue;
+use super::layout::Layout;
+use super::rules::RuleSet;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Serializable state of a cell: (value, is_white, is_fixed, small_values, marking, small_value_markings)
+pub type CellData = (i32, bool, bool, Vec<bool>, i32, Vec<i32>);
+
+// Serializable game state: the rules, outside clues and layout of the puzzle and the state of
+// all cells. The layout is only stored for boards of several grids.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Savegame {
+    #[serde(default)]
+    pub rules: RuleSet,
+    #[serde(default)]
+    pub clues: Vec<Clue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<Layout>,
+    pub cells: Vec<CellData>,
+}
+
+impl Savegame {
+    // Savegame of a board, without small values and markings
+    pub fn of_board(board: &Board) -> Savegame {
+        let layout = Some(board.layout().clone()).filter(|layout| !layout.is_single());
+        Savegame { rules: board.rules(), clues: board.clues().to_vec(), layout, cells: cell_data_of_board(board) }
+    }
+
+    // The layout of the board: given, or a single grid filling the board
+    pub fn layout(&self) -> Layout {
+        self.layout.clone().unwrap_or_else(|| {
+            Layout::single(size_of_cell_count(self.cells.len()).unwrap_or(0))
+        })
+    }
+
+    // The board part of the savegame (without small values and markings)
+    pub fn board(&self) -> Board {
+        board_from_cell_data(&self.cells).with_layout(self.layout()).with_rules(self.rules).with_clues(self.clues.clone())
+    }
+}
+
+// File formats for puzzles:
+// - Json: the savegame format
+// - Text: one line per row of the board, '.' is an empty white cell, '1'-'9' and 'A'-'C' a white
+//   cell with the number 1-12, '#' an empty black cell and 'a'-'l' a black cell with the number 1-12.
+//   Puzzles of variants start with a line naming their rules, e.g. "rules: sudoku", followed by
+//   a line per outside clue, e.g. "clue: left 3 sum 17" (side, row/column from 1, sum/min/max, value).
+//   Boards of several grids have a line "layout: samurai" (or the grid size and the row,column of each
+//   grid, e.g. "layout: 9 0,0 6,6"), their lines cover all grids and cells outside the grids are '#'.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Text
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "json" => Some(Format::Json),
+            "txt" | "text" => Some(Format::Text),
+            _ => None
+        }
+    }
+
+    // Guess the format from the file extension, text by default
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Format::Json,
+            _ => Format::Text
+        }
+    }
+}
+
+// Parse a savegame, also accepts savegames from before rules were stored (a list of CellData)
+// and from before markings existed: (value, is_white, is_fixed, small_values)
+pub fn parse_savegame(json_data: &str) -> Result<Savegame, String> {
+    let savegame: Savegame = serde_json::from_str(json_data)
+        .or_else(|_| serde_json::from_str::<Vec<CellData>>(json_data)
+            .map(|cells| Savegame { rules: RuleSet::classic(), clues: vec![], layout: None, cells }))
+        .or_else(|_| serde_json::from_str::<Vec<(i32, bool, bool, Vec<bool>)>>(json_data)
+            .map(|cells_data| cells_data.into_iter()
+                .map(|(value, is_white, is_fixed, small_values)| {
+                    let small_value_markings = vec![0; small_values.len()];
+                    (value, is_white, is_fixed, small_values, 0, small_value_markings)
+                })
+                .collect())
+            .map(|cells| Savegame { rules: RuleSet::classic(), clues: vec![], layout: None, cells }))
+        .map_err(|_| "unable to parse JSON.".to_string())?;
+    // The size of the board is given by the number of cells, or by the layout of its grids
+    let size = match &savegame.layout {
+        Some(layout) if layout.is_valid() && layout.size() * layout.size() == savegame.cells.len() => layout.size(),
+        Some(_) => return Err("invalid layout.".into()),
+        None => size_of_cell_count(savegame.cells.len()).ok_or_else(|| "invalid number of cells.".to_string())?,
+    };
+    // Digits go up to the size of the grids
+    let max_digit = savegame.layout().grid_size;
+    for data in &savegame.cells {
+        // Check validity of cell data
+        if !(data.0 == -1 || (data.0 >= 1 && data.0 <= max_digit as i32)) {
+            return Err("invalid cell value.".into());
+        }
+        if data.3.len() != max_digit {
+            return Err("invalid small values.".into());
+        }
+        if !(data.4 >= 0 && data.5.len() == max_digit && data.5.iter().all(|&m| m >= 0)) {
+            return Err("invalid markings.".into());
+        }
+    }
+    if !savegame.clues.iter().all(|clue| savegame.layout.is_none() && clue.is_valid(size)) {
+        return Err("invalid outside clue.".into());
+    }
+    Ok(savegame)
+}
+
+// The board part of savegame data (without small values and markings)
+pub fn board_from_cell_data(cells_data: &[CellData]) -> Board {
+    Board::from_cells(cells_data.iter()
+        .map(|&(value, is_white, is_fixed, _, _, _)| {
+            let kind = if is_white { CellKind::White } else { CellKind::Black };
+            BoardCell::new(kind, Digit::new(value), is_fixed)
+        })
+        .collect())
+}
+
+// Savegame data of a board, without small values and markings
+pub fn cell_data_of_board(board: &Board) -> Vec<CellData> {
+    let max_digit = board.max_digit();
+    board.cells().iter()
+        .map(|cell| (cell.value(), cell.is_white(), cell.is_fixed, vec![false; max_digit], 0, vec![0; max_digit]))
+        .collect()
+}
+
+// Parse the text format. Lines are rows on screen, which run along pos_x.
+pub fn parse_text(text: &str) -> Result<Board, String> {
+    let mut lines = text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).collect::<Vec<_>>();
+    let mut rules = RuleSet::classic();
+    if let Some(name) = lines.first().and_then(|line| line.strip_prefix("rules:")) {
+        rules = RuleSet::from_name(name.trim()).ok_or_else(|| format!("unknown rules '{}'.", name.trim()))?;
+        lines.remove(0);
+    }
+    let mut layout = None;
+    if let Some(name) = lines.first().and_then(|line| line.strip_prefix("layout:")) {
+        layout = Some(Layout::from_name(name.trim()).ok_or_else(|| format!("invalid layout '{}'.", name.trim()))?);
+        lines.remove(0);
+    }
+    let mut clues = vec![];
+    while let Some(text) = lines.first().and_then(|line| line.strip_prefix("clue:")) {
+        clues.push(Clue::from_text(text).ok_or_else(|| format!("invalid clue '{}'.", text.trim()))?);
+        lines.remove(0);
+    }
+    // The size of the board is given by the number of lines, or by the layout
+    let size = lines.len();
+    if let Some(layout) = &layout {
+        if size != layout.size() || lines.iter().any(|line| line.chars().count() != size) {
+            return Err(format!("expected {} lines of {} characters for the layout.", layout.size(), layout.size()));
+        }
+    } else if !(MIN_SIZE..=MAX_SIZE).contains(&size) || lines.iter().any(|line| line.chars().count() != size) {
+        return Err(format!("expected {} to {} lines with as many characters as there are lines.", MIN_SIZE, MAX_SIZE));
+    }
+    let layout = layout.unwrap_or_else(|| Layout::single(size));
+    let mut cells = vec![];
+    for i in 0..size * size {
+        let c = lines[i % size].chars().nth(i / size).unwrap();
+        let (digit, kind) = match c {
+            '.' => (None, CellKind::White),
+            '#' => (None, CellKind::Black),
+            '1'..='9' | 'A'..='Z' => (Digit::from_char(c), CellKind::White),
+            'a'..='z' => (Digit::new(c as i32 - 'a' as i32 + 1), CellKind::Black),
+            _ => (None, CellKind::Black)
+        };
+        let is_valid = c == '.' || c == '#' || matches!(digit, Some(digit) if digit.get() <= layout.grid_size as i32);
+        if !is_valid {
+            return Err(format!("invalid character '{}'.", c));
+        }
+        cells.push(BoardCell::new(kind, digit, true));
+    }
+    if !clues.iter().all(|clue| layout.is_single() && clue.is_valid(size)) {
+        return Err("outside clue beyond the board.".into());
+    }
+    Ok(Board::from_cells(cells).with_layout(layout).with_rules(rules).with_clues(clues))
+}
+
+// Write the text format. Whether a digit is fixed is lost.
+pub fn format_text(board: &Board) -> String {
+    let size = board.size();
+    let mut text = String::new();
+    if board.rules() != RuleSet::classic() {
+        text.push_str(&format!("rules: {}\n", board.rules().name()));
+    }
+    if !board.layout().is_single() {
+        text.push_str(&format!("layout: {}\n", board.layout().name()));
+    }
+    for clue in board.clues() {
+        text.push_str(&format!("clue: {}\n", clue.to_text()));
+    }
+    for row in 0..size {
+        for column in 0..size {
+            let cell = &board[size * column + row];
+            text.push(match (cell.digit, cell.kind) {
+                (None, CellKind::White) => '.',
+                (None, CellKind::Black) => '#',
+                (Some(digit), CellKind::White) => digit.to_char(),
+                (Some(digit), CellKind::Black) => (b'a' + digit.get() as u8 - 1) as char,
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// Parse a board in the given format
+pub fn parse_puzzle(data: &str, format: Format) -> Result<Board, String> {
+    match format {
+        Format::Json => parse_savegame(data).map(|savegame| savegame.board()),
+        Format::Text => parse_text(data)
+    }
+}
+
+// Write a board in the given format
+pub fn format_puzzle(board: &Board, format: Format) -> String {
+    match format {
+        Format::Json => {
+            serde_json::to_string(&Savegame::of_board(board)).expect("Unable to create JSON.")
+        },
+        Format::Text => format_text(board)
+    }
+}
+
+// Short ID of a puzzle, the same for boards with the same puzzle: a hash (FNV-1a) of its text
+// format, without the digits entered by the player
+pub fn puzzle_id(board: &Board) -> String {
+    let hash = format_text(&board.puzzle()).bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    format!("{:08x}", hash >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clues::{ClueKind, Side};
+
+    const PUZZLE: &str = "c...a.\n.2.##.\n#5.##.\n#..#3.\n...##6\n.6#1.#\n";
+
+    // A Samurai board with a digit in each grid, including the boxes shared with the middle grid
+    fn samurai_board() -> Board {
+        let layout = Layout::samurai();
+        let n = layout.size();
+        let mut board = Board::empty_with_layout(layout).with_rules(RuleSet::sudoku());
+        for &(row, column, value) in &[(0, 0, 1), (7, 7, 5), (10, 10, 9), (13, 13, 2), (20, 20, 4), (0, 20, 3)] {
+            board[n * column + row] = BoardCell::new(CellKind::White, Digit::new(value), true);
+        }
+        board[n * 3 + 3] = BoardCell::new(CellKind::Black, None, false);
+        board
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let board = parse_text(PUZZLE).unwrap();
+        assert_eq!(board.size(), 6);
+        assert_eq!(board[6 + 1].digit, Digit::new(2));
+        assert!(!board[0].is_white());
+        assert_eq!(format_text(&board), PUZZLE);
+    }
+
+    #[test]
+    fn text_round_trip_of_variants() {
+        let text = "rules: sudoku\nclue: left 1 sum 6\nclue: bottom 4 max 3\n1###\n.1.3\n.b#.\nd#1#\n";
+        let board = parse_text(text).unwrap();
+        assert_eq!(board.rules(), RuleSet::sudoku());
+        assert_eq!(board.clues(), &[
+            Clue { side: Side::Left, line: 0, kind: ClueKind::Sum, value: 6 },
+            Clue { side: Side::Bottom, line: 3, kind: ClueKind::Max, value: 3 },
+        ]);
+        assert_eq!(format_text(&board), text);
+    }
+
+    #[test]
+    fn large_boards() {
+        let mut board = Board::empty(12);
+        board[0] = BoardCell::new(CellKind::White, Digit::new(12), true);
+        board[1] = BoardCell::new(CellKind::Black, Digit::new(10), true);
+        let text = format_text(&board);
+        assert!(text.starts_with("C"));
+        assert!(text.lines().nth(1).unwrap().starts_with("j"));
+        assert_eq!(parse_text(&text).unwrap(), board);
+    }
+
+    #[test]
+    fn samurai_round_trip() {
+        let board = samurai_board();
+        let text = format_text(&board);
+        assert!(text.starts_with("rules: sudoku\nlayout: samurai\n"));
+        assert_eq!(text.lines().count(), 2 + 21);
+        // Cells outside the grids are black
+        assert_eq!(text.lines().nth(2 + 10).unwrap().chars().next(), Some('#'));
+        assert_eq!(parse_text(&text).unwrap(), board);
+
+        let json = format_puzzle(&board, Format::Json);
+        let savegame = parse_savegame(&json).unwrap();
+        assert_eq!(savegame.layout, Some(Layout::samurai()));
+        assert_eq!(savegame.board(), board);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let board = parse_text("rules: sudoku\nclue: top 2 min 2\n1###\n.1.3\n.b#.\nd#1#\n").unwrap();
+        let json = format_puzzle(&board, Format::Json);
+        // Single grids don't store their layout
+        assert!(!json.contains("layout"));
+        assert_eq!(parse_puzzle(&json, Format::Json).unwrap(), board);
+        assert_eq!(format_puzzle(&parse_puzzle(&json, Format::Json).unwrap(), Format::Text), format_text(&board));
+    }
+
+    #[test]
+    fn old_savegames() {
+        let cells = vec![(1, true, true, vec![false; 4]); 16];
+        let savegame = parse_savegame(&serde_json::to_string(&cells).unwrap()).unwrap();
+        assert_eq!(savegame.rules, RuleSet::classic());
+        assert_eq!(savegame.board().size(), 4);
+        assert_eq!(savegame.cells[0], (1, true, true, vec![false; 4], 0, vec![0; 4]));
+    }
+
+    #[test]
+    fn invalid_puzzles() {
+        assert!(parse_text("...\n...\n...\n").is_err());
+        assert!(parse_text("....\n....\n....\n...\n").is_err());
+        assert!(parse_text("5...\n....\n....\n....\n").is_err());
+        assert!(parse_text("rules: chess\n....\n....\n....\n....\n").is_err());
+        assert!(parse_text("clue: left 5 sum 3\n....\n....\n....\n....\n").is_err());
+        assert!(parse_savegame("[1, 2, 3]").is_err());
+        assert!(parse_savegame(&serde_json::to_string(&vec![(7, true, true, vec![false; 4]); 16]).unwrap()).is_err());
+    }
+
+    #[test]
+    fn ids_ignore_the_player_digits() {
+        let puzzle = parse_text(PUZZLE).unwrap();
+        let mut board = puzzle.clone();
+        board[1] = BoardCell::new(CellKind::White, Digit::new(1), false);
+        assert_eq!(puzzle_id(&board), puzzle_id(&puzzle));
+        assert_eq!(puzzle_id(&puzzle).len(), 8);
+        assert_ne!(puzzle_id(&puzzle), puzzle_id(&puzzle.clone().with_rules(RuleSet::sudoku())));
+    }
+}
@@ -0,0 +1,129 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{Board, BoardCell, CellKind, Digit};
+use super::layout::Layout;
+use super::rules::RuleSet;
+use super::constraints::Constraints;
+use super::solver::{solve_backtrack_cancellable, Str8tsSolution};
+use rand::prelude::SliceRandom;
+use rand::Rng;
+
+// Generates a random puzzle of one or more grids with given probabilities for
+// fixed-number cells and white cells. Usually the resulting
+// puzzle is not valid, let alone has a unique solution.
+pub fn random_board(layout: &Layout, p_fixed: f64, p_white: f64) -> Board {
+    random_board_with_rng(&mut rand::thread_rng(), layout, p_fixed, p_white)
+}
+
+// Like random_board, with a given random number generator (e.g. a seeded one).
+// Cells outside the grids stay black.
+pub fn random_board_with_rng<R: Rng>(rng: &mut R, layout: &Layout, p_fixed: f64, p_white: f64) -> Board {
+    let mut cells = vec!();
+    for _ in 0..layout.size() * layout.size() {
+        // Determine is_fixed and is_white randomly
+        let is_fixed = rng.gen_range(0.0..1.0) < p_fixed;
+        let is_white = rng.gen_range(0.0..1.0) < p_white;
+        let digit = if is_fixed { Digit::new(rng.gen_range(1..=layout.grid_size as i32)) } else { None };
+        let kind = if is_white { CellKind::White } else { CellKind::Black };
+        cells.push(BoardCell::new(kind, digit, is_fixed));
+    }
+    Board::from_cells(cells).with_layout(layout.clone())
+}
+
+// Maximum number of restrictions the generator lifts or imposes
+pub const GENERATOR_STEPS: usize = 1000;
+
+// Maximum number of values the solver tries in one step. Lifting a restriction is usually
+// faster than deciding such a board.
+const MAX_SOLVER_NODES: usize = 100_000;
+
+// Maximum number of random picks for the next cell to fix in one step
+const MAX_CELL_ATTEMPTS: usize = 10000;
+
+// Generates a puzzle with a unique solution. The generator starts from a random board
+// with the given probabilities of fixed and white cells, then lifts or imposes
+// restrictions until the solution is unique. is_cancelled is called regularly with the
+// current step; returns None if cancelled or if no puzzle was found in GENERATOR_STEPS steps.
+pub fn generate_puzzle<R: Rng>(rng: &mut R, layout: &Layout, rules: RuleSet, p_fixed: f64, p_white: f64,
+        is_cancelled: &dyn Fn(usize) -> bool) -> Option<Board> {
+    let mut board = random_board_with_rng(rng, layout, 0.0, p_white).with_rules(rules);
+    let mut fixed_indices = vec![];
+
+    let constraints = Constraints::new(&board);
+    for i in 0..board.len() {
+        if layout.is_used(i) && rng.gen_range(0.0..1.0) < p_fixed {
+            let possible_values = constraints.candidates(i, &board);
+            if let Some(&digit) = possible_values.choose(rng) {
+                board[i] = BoardCell::new(board[i].kind, Some(digit), true);
+                fixed_indices.push(i);
+            }
+        }
+    }
+
+    let mut solution = None;
+    for i in 0..GENERATOR_STEPS {
+        let is_too_hard = std::cell::Cell::new(false);
+        let result = solve_backtrack_cancellable(board.clone(), &|nodes| {
+            if nodes >= MAX_SOLVER_NODES {
+                is_too_hard.set(true);
+            }
+            is_too_hard.get() || is_cancelled(i)
+        });
+        // A board the solver can't decide in time is treated like one without solution
+        let result = match result {
+            Some(result) => result,
+            None if is_too_hard.get() => Str8tsSolution::None,
+            None => return None
+        };
+        match result {
+            Str8tsSolution::None => {
+                // Lift some restriction
+                if let Some(cell_index) = fixed_indices.pop() {
+                    board[cell_index] = BoardCell::new(board[cell_index].kind, None, false);
+                } else {
+                    // No solution even without fixed numbers
+                    break;
+                }
+            },
+            Str8tsSolution::Unique(solution_board) => {
+                solution = Some(solution_board);
+                break;
+            },
+            Str8tsSolution::Multiple(ref solution_board) => {
+                // Impose more restrictions from found solution
+                let mut cell_index = rng.gen_range(0..board.len());
+
+                const P_FILL_BLACK: f64 = 0.3;
+                let mut attempts = 0;
+                while !layout.is_used(cell_index) || (board[cell_index].is_fixed || solution_board[cell_index].digit.is_none()) &&
+                        (board[cell_index].is_white() || solution_board[cell_index].digit.is_some()
+                        || constraints.candidates(cell_index, &board).is_empty()
+                        || rng.gen_range(0.0..1.0) > P_FILL_BLACK) {
+                    // Give up if no cell can take another restriction
+                    attempts += 1;
+                    if attempts > MAX_CELL_ATTEMPTS || is_cancelled(i) {
+                        return None;
+                    }
+                    cell_index = rng.gen_range(0..board.len());
+                }
+                let digit = match solution_board[cell_index].digit {
+                    // Make an empty white cell fixed
+                    Some(digit) => digit,
+                    // Make an empty black cell fixed
+                    None => *constraints.candidates(cell_index, &board).choose(rng).unwrap()
+                };
+                board[cell_index] = BoardCell::new(board[cell_index].kind, Some(digit), true);
+                fixed_indices.push(cell_index);
+            },
+        }
+    }
+    // Keep only the fixed numbers of the solution
+    solution.map(|solution_board| solution_board.puzzle())
+}
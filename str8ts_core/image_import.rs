@@ -0,0 +1,280 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{Board, BoardCell, CellKind, Digit, DEFAULT_SIZE, MAX_SIZE, MIN_SIZE};
+use super::glyphs::{glyph_pixel, GLYPH_HEIGHT, GLYPH_WIDTH};
+use image::GrayImage;
+use std::path::Path;
+
+// Digits are compared on a canvas of twice the glyph size
+const CANVAS_WIDTH: usize = 2 * GLYPH_WIDTH;
+const CANVAS_HEIGHT: usize = 2 * GLYPH_HEIGHT;
+
+// Part of a cell left out on each side, so that the grid lines don't count as ink
+const CELL_MARGIN: f64 = 0.15;
+
+// Cells with less ink than this part of their area are empty
+const MIN_INK: f64 = 0.02;
+
+// Grid lines are at least this part as dark as the outer border
+const MIN_LINE_DARKNESS: f64 = 0.75;
+
+// Read a puzzle from a PNG or JPEG image. The board size is detected unless it is given.
+pub fn import_image(path: &Path, size: Option<usize>) -> Result<Board, String> {
+    let image = image::open(path).map_err(|error| format!("unable to read image: {}.", error))?;
+    board_from_image(&image.to_luma8(), size)
+}
+
+// Recognize a puzzle in a grayscale image that shows a single grid on a light background:
+// find the grid, then decide per cell whether it is black and which digit it shows. Digits
+// are matched against the glyphs of the built-in font, so printed fonts may be misread and
+// the result should be checked in the edit phase. All digits become fixed numbers.
+pub fn board_from_image(image: &GrayImage, size: Option<usize>) -> Result<Board, String> {
+    let mask = DarkMask::new(image);
+    let grid = mask.find_grid().ok_or_else(|| "no grid found in the image.".to_string())?;
+    let size = match size {
+        Some(size) if (MIN_SIZE..=MAX_SIZE).contains(&size) => size,
+        Some(size) => return Err(format!("unsupported board size {}.", size)),
+        None => mask.detect_size(&grid),
+    };
+    let templates = Digit::all(size)
+        .filter_map(|digit| ink_canvas(GLYPH_WIDTH, GLYPH_HEIGHT, |x, y| glyph_pixel(digit, x, y))
+            .map(|canvas| (digit, canvas)))
+        .collect::<Vec<_>>();
+
+    let mut cells = vec![];
+    for i in 0..size * size {
+        // On screen, cell i is in row i % size and column i / size
+        let cell = grid.cell(i % size, i / size, size);
+        let (x0, y0) = (cell.x.round() as usize, cell.y.round() as usize);
+        let (width, height) = (cell.width.round().max(1.0) as usize, cell.height.round().max(1.0) as usize);
+        let dark_count = (y0..y0 + height).flat_map(|y| (x0..x0 + width).map(move |x| (x, y)))
+            .filter(|&(x, y)| mask.is_dark(x, y))
+            .count();
+        let is_black = dark_count * 2 > width * height;
+        // Digits are dark in white cells and light in black cells
+        let ink = |x: usize, y: usize| mask.is_dark(x0 + x, y0 + y) != is_black;
+        let ink_count = if is_black { width * height - dark_count } else { dark_count };
+        let digit = if (ink_count as f64) < MIN_INK * (width * height) as f64 {
+            None
+        } else {
+            ink_canvas(width, height, ink).and_then(|canvas| best_match(&canvas, &templates))
+        };
+        let kind = if is_black { CellKind::Black } else { CellKind::White };
+        cells.push(BoardCell::new(kind, digit, digit.is_some()));
+    }
+    Ok(Board::from_cells(cells))
+}
+
+// A rectangle in image coordinates
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Rect {
+    // Inner part of a cell of a grid of the given size, without the margins
+    fn cell(&self, row: usize, column: usize, size: usize) -> Rect {
+        let (width, height) = (self.width / size as f64, self.height / size as f64);
+        Rect {
+            x: self.x + (column as f64 + CELL_MARGIN) * width,
+            y: self.y + (row as f64 + CELL_MARGIN) * height,
+            width: width * (1.0 - 2.0 * CELL_MARGIN),
+            height: height * (1.0 - 2.0 * CELL_MARGIN),
+        }
+    }
+}
+
+// Pixels darker than a threshold chosen by Otsu's method, which separates ink from paper
+struct DarkMask {
+    width: usize,
+    height: usize,
+    is_dark: Vec<bool>,
+}
+
+impl DarkMask {
+    fn new(image: &GrayImage) -> DarkMask {
+        let mut histogram = [0usize; 256];
+        for pixel in image.pixels() {
+            histogram[pixel.0[0] as usize] += 1;
+        }
+        let threshold = otsu_threshold(&histogram);
+        DarkMask {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            is_dark: image.pixels().map(|pixel| pixel.0[0] <= threshold).collect(),
+        }
+    }
+
+    fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.is_dark[self.width * y + x]
+    }
+
+    // Number of dark pixels per column within the given rows, and per row within the given columns
+    fn column_profile(&self, rows: std::ops::Range<usize>) -> Vec<usize> {
+        (0..self.width).map(|x| rows.clone().filter(|&y| self.is_dark(x, y)).count()).collect()
+    }
+
+    fn row_profile(&self, columns: std::ops::Range<usize>) -> Vec<usize> {
+        (0..self.height).map(|y| columns.clone().filter(|&x| self.is_dark(x, y)).count()).collect()
+    }
+
+    // The outer border of the grid: the outermost columns and rows that are about as dark
+    // as the darkest ones
+    fn find_grid(&self) -> Option<Rect> {
+        let (left, right) = outer_lines(&self.column_profile(0..self.height))?;
+        let (top, bottom) = outer_lines(&self.row_profile(0..self.width))?;
+        if right - left < 2 * MIN_SIZE || bottom - top < 2 * MIN_SIZE {
+            return None;
+        }
+        Some(Rect { x: left as f64, y: top as f64, width: (right + 1 - left) as f64, height: (bottom + 1 - top) as f64 })
+    }
+
+    // The number of cells per row/column: the largest size that has a dark line wherever
+    // it needs one. (The lines of e.g. a 4×4 grid are also lines of a 12×12 grid.)
+    fn detect_size(&self, grid: &Rect) -> usize {
+        let (x0, y0) = (grid.x as usize, grid.y as usize);
+        let (x1, y1) = (x0 + grid.width as usize, y0 + grid.height as usize);
+        let columns = self.column_profile(y0..y1)[x0..x1].to_vec();
+        let rows = self.row_profile(x0..x1)[y0..y1].to_vec();
+        (MIN_SIZE..=MAX_SIZE).rev()
+            .find(|&size| has_grid_lines(&columns, size) && has_grid_lines(&rows, size))
+            .unwrap_or(DEFAULT_SIZE)
+    }
+}
+
+// Threshold that maximizes the variance between the dark and the light pixels
+fn otsu_threshold(histogram: &[usize; 256]) -> u8 {
+    let total = histogram.iter().sum::<usize>() as f64;
+    let sum = histogram.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum::<f64>();
+    let (mut dark_count, mut dark_sum) = (0.0, 0.0);
+    let (mut threshold, mut best_variance) = (127, f64::MIN);
+    for (value, &count) in histogram.iter().enumerate().take(255) {
+        dark_count += count as f64;
+        dark_sum += value as f64 * count as f64;
+        let light_count = total - dark_count;
+        if dark_count == 0.0 || light_count == 0.0 {
+            continue;
+        }
+        let difference = dark_sum / dark_count - (sum - dark_sum) / light_count;
+        let variance = dark_count * light_count * difference * difference;
+        if variance > best_variance {
+            best_variance = variance;
+            threshold = value as u8;
+        }
+    }
+    threshold
+}
+
+// First and last index whose count is at least half the maximum
+fn outer_lines(profile: &[usize]) -> Option<(usize, usize)> {
+    let max = *profile.iter().max()?;
+    if max == 0 {
+        return None;
+    }
+    let first = profile.iter().position(|&count| 2 * count >= max)?;
+    let last = profile.iter().rposition(|&count| 2 * count >= max)?;
+    Some((first, last))
+}
+
+// Check if there is a dark line at each inner grid line of a size. Lines are searched for
+// a little around their expected position.
+fn has_grid_lines(profile: &[usize], size: usize) -> bool {
+    let border = profile.iter().cloned().max().unwrap_or(0) as f64;
+    let tolerance = profile.len() / 100 + 1;
+    let pitch = profile.len() as f64 / size as f64;
+    (1..size).all(|k| {
+        let center = (k as f64 * pitch).round() as usize;
+        let darkest = profile[center.saturating_sub(tolerance)..(center + tolerance + 1).min(profile.len())]
+            .iter().cloned().max().unwrap_or(0);
+        darkest as f64 >= MIN_LINE_DARKNESS * border
+    })
+}
+
+// Scale the ink of an area to the comparison canvas, keeping its aspect ratio. Each canvas
+// pixel holds the part of it covered by ink. None if there is no ink.
+fn ink_canvas(width: usize, height: usize, ink: impl Fn(usize, usize) -> bool) -> Option<Vec<f64>> {
+    // Bounding box of the ink
+    let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).filter(|&(x, y)| ink(x, y));
+    let (mut left, mut top, mut right, mut bottom) = (width, height, 0, 0);
+    for (x, y) in pixels {
+        left = left.min(x);
+        top = top.min(y);
+        right = right.max(x + 1);
+        bottom = bottom.max(y + 1);
+    }
+    if left >= right {
+        return None;
+    }
+    let (ink_width, ink_height) = ((right - left) as f64, (bottom - top) as f64);
+    let scale = (CANVAS_WIDTH as f64 / ink_width).min(CANVAS_HEIGHT as f64 / ink_height);
+    let offset_x = (CANVAS_WIDTH as f64 - ink_width * scale) / 2.0;
+    let offset_y = (CANVAS_HEIGHT as f64 - ink_height * scale) / 2.0;
+    // Sample 3×3 points per canvas pixel
+    const SAMPLES: usize = 3;
+    let mut canvas = vec![0.0; CANVAS_WIDTH * CANVAS_HEIGHT];
+    for (k, value) in canvas.iter_mut().enumerate() {
+        let (cx, cy) = (k % CANVAS_WIDTH, k / CANVAS_WIDTH);
+        let mut hits = 0;
+        for sample in 0..SAMPLES * SAMPLES {
+            let px = cx as f64 + (sample % SAMPLES) as f64 / SAMPLES as f64 + 0.5 / SAMPLES as f64;
+            let py = cy as f64 + (sample / SAMPLES) as f64 / SAMPLES as f64 + 0.5 / SAMPLES as f64;
+            let (sx, sy) = ((px - offset_x) / scale, (py - offset_y) / scale);
+            if sx >= 0.0 && sy >= 0.0 && sx < ink_width && sy < ink_height && ink(left + sx as usize, top + sy as usize) {
+                hits += 1;
+            }
+        }
+        *value = hits as f64 / (SAMPLES * SAMPLES) as f64;
+    }
+    Some(canvas)
+}
+
+// The digit whose template differs least from the canvas
+fn best_match(canvas: &[f64], templates: &[(Digit, Vec<f64>)]) -> Option<Digit> {
+    let difference = |template: &[f64]| canvas.iter().zip(template).map(|(a, b)| (a - b).abs()).sum::<f64>();
+    templates.iter()
+        .map(|(digit, template)| (*digit, difference(template)))
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(digit, _)| digit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{format_text, parse_text};
+    use crate::themes::BUILTIN_THEMES;
+    use crate::thumbnail::render_thumbnail;
+    use image::DynamicImage;
+
+    // Import the picture of a board drawn like the thumbnails
+    fn import_thumbnail(text: &str, size: Option<usize>) -> Board {
+        let board = parse_text(text).unwrap();
+        let thumbnail = render_thumbnail(&board, 480, &BUILTIN_THEMES[0].board_colors());
+        board_from_image(&DynamicImage::ImageRgba8(thumbnail).to_luma8(), size).unwrap()
+    }
+
+    #[test]
+    fn thumbnail_round_trip() {
+        for text in &["c...a.\n.2.##.\n#5.##.\n#..#3.\n...##6\n.6#1.#\n", "1###\n.1.3\n.b#.\nd#1#\n",
+                "..#5.1789\n2.......#\n#3....b..\n...4#....\n.6.......\n....f.3..\n..a......\n#.......2\n9....#...\n"] {
+            assert_eq!(format_text(&import_thumbnail(text, None)), *text);
+        }
+    }
+
+    #[test]
+    fn given_size() {
+        let text = "1###\n.1.3\n.b#.\nd#1#\n";
+        assert_eq!(format_text(&import_thumbnail(text, Some(4))), text);
+        assert!(board_from_image(&GrayImage::from_pixel(100, 100, image::Luma([255])), None).is_err());
+        let thumbnail = render_thumbnail(&parse_text(text).unwrap(), 200, &BUILTIN_THEMES[0].board_colors());
+        assert!(board_from_image(&DynamicImage::ImageRgba8(thumbnail).to_luma8(), Some(13)).is_err());
+    }
+}
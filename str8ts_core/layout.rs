@@ -0,0 +1,111 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{MAX_SIZE, MIN_SIZE};
+use serde::{Deserialize, Serialize};
+
+// Largest number of cells per row/column of the area holding all grids of a board
+pub const MAX_AREA_SIZE: usize = 21;
+
+// Position of a grid's top left cell, in rows and columns on screen
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct GridPosition {
+    pub row: usize,
+    pub column: usize,
+}
+
+// How the grids of a board are arranged: a single grid, or several grids of the same size
+// that share cells where they overlap (e.g. Samurai). The board is a square area large enough
+// for all grids, where cell i is at row i % n, column i / n on screen (n = area size).
+// Cells outside all grids are unused.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Layout {
+    pub grid_size: usize,
+    pub grids: Vec<GridPosition>,
+}
+
+impl Layout {
+    pub fn single(size: usize) -> Layout {
+        Layout { grid_size: size, grids: vec![GridPosition { row: 0, column: 0 }] }
+    }
+
+    // Five 9×9 grids: four in the corners, overlapping a grid in the middle by a 3×3 box
+    pub fn samurai() -> Layout {
+        let grids = [(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)].iter()
+            .map(|&(row, column)| GridPosition { row, column })
+            .collect();
+        Layout { grid_size: 9, grids }
+    }
+
+    pub fn is_single(&self) -> bool {
+        self.grids.len() == 1
+    }
+
+    // Number of cells per row/column of the area holding all grids
+    pub fn size(&self) -> usize {
+        self.grids.iter().map(|grid| grid.row.max(grid.column) + self.grid_size).max().unwrap_or(0)
+    }
+
+    // Check the grid size, and that all grids fit into the largest area
+    pub fn is_valid(&self) -> bool {
+        self.grid_size >= MIN_SIZE && self.grid_size <= MAX_SIZE && !self.grids.is_empty()
+            && self.size() <= MAX_AREA_SIZE
+    }
+
+    pub fn index(&self, row: usize, column: usize) -> usize {
+        self.size() * column + row
+    }
+
+    // Row and column on screen of a cell
+    pub fn position(&self, index: usize) -> (usize, usize) {
+        (index % self.size(), index / self.size())
+    }
+
+    // Indices of the grids a cell belongs to; none for unused cells, several for shared cells
+    pub fn grids_of(&self, index: usize) -> Vec<usize> {
+        let (row, column) = self.position(index);
+        self.grids.iter().enumerate()
+            .filter(|(_, grid)| row >= grid.row && row < grid.row + self.grid_size
+                && column >= grid.column && column < grid.column + self.grid_size)
+            .map(|(k, _)| k)
+            .collect()
+    }
+
+    pub fn is_used(&self, index: usize) -> bool {
+        !self.grids_of(index).is_empty()
+    }
+
+    // Cell index of the cell at the given row and column of the k-th grid
+    pub fn grid_cell(&self, k: usize, row: usize, column: usize) -> usize {
+        self.index(self.grids[k].row + row, self.grids[k].column + column)
+    }
+
+    // The text format's name of the layout: "samurai", or the grid size followed by the
+    // positions of the grids, e.g. "9 0,0 6,6"
+    pub fn name(&self) -> String {
+        if *self == Layout::samurai() {
+            return "samurai".into();
+        }
+        let grids = self.grids.iter().map(|grid| format!("{},{}", grid.row, grid.column)).collect::<Vec<_>>();
+        format!("{} {}", self.grid_size, grids.join(" "))
+    }
+
+    pub fn from_name(name: &str) -> Option<Layout> {
+        if name == "samurai" {
+            return Some(Layout::samurai());
+        }
+        let mut parts = name.split_whitespace();
+        let grid_size = parts.next()?.parse().ok()?;
+        let grids = parts.map(|part| {
+            let (row, column) = part.split_once(',')?;
+            Some(GridPosition { row: row.parse().ok()?, column: column.parse().ok()? })
+        }).collect::<Option<Vec<_>>>()?;
+        Some(Layout { grid_size, grids }).filter(Layout::is_valid)
+    }
+}
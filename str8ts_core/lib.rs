@@ -0,0 +1,37 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+// The puzzle engine: board, rules, outside clues, solver, generator, file formats, puzzle
+// collections, image import, printing, thumbnails and the colors of the built-in themes. It
+// does not depend on sixtyfps, the UI converts between its Cell and BoardCell.
+
+pub mod board;
+pub mod store;
+pub mod row;
+pub mod rules;
+pub mod clues;
+pub mod layout;
+pub mod constraints;
+pub mod solver;
+pub mod generator;
+pub mod formats;
+pub mod collection;
+pub mod glyphs;
+pub mod image_import;
+pub mod print;
+pub mod thumbnail;
+pub mod themes;
+
+pub use board::{Board, BoardCell, CellKind, Digit, DEFAULT_SIZE, MAX_SIZE, MIN_SIZE};
+pub use clues::{Clue, ClueKind, Side};
+pub use layout::Layout;
+pub use constraints::{Constraint, Constraints, Violation};
+pub use row::Row;
+pub use rules::RuleSet;
+pub use store::{BitBoard, CellStore};
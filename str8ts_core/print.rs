@@ -0,0 +1,478 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{Board, Digit};
+use super::clues::Side;
+use super::rules::box_shape;
+
+// An A4 page and its margins, in mm
+const PAGE_WIDTH: f64 = 210.0;
+const PAGE_HEIGHT: f64 = 297.0;
+const MARGIN: f64 = 15.0;
+
+// Space between the puzzles of a page, in mm
+const GAP: f64 = 10.0;
+
+// Width of the ring of outside clues, in cells
+const CLUE_RING: f64 = 0.7;
+
+// Line widths in mm
+const CELL_LINE: f64 = 0.2;
+const BOX_LINE: f64 = 0.5;
+const GRID_LINE: f64 = 0.8;
+
+// Gray of the digits entered by the player and of pencil marks (0 is black, 1 white)
+const PLAYER_GRAY: f64 = 0.35;
+
+// Supported numbers of puzzles per page
+pub const PUZZLES_PER_PAGE: [usize; 4] = [1, 2, 4, 6];
+
+// A puzzle to print. Fixed digits are printed bold, other digits as entered by the player.
+// Pencil marks are printed in empty white cells, they are given per cell or left empty.
+// Puzzles with a solution also appear on the solution pages at the end.
+pub struct PrintPuzzle {
+    pub board: Board,
+    pub pencil_marks: Vec<Vec<Digit>>,
+    pub title: String,
+    pub difficulty: Option<String>,
+    pub id: String,
+    pub solution: Option<Board>,
+}
+
+// Print puzzles on A4 pages as SVG, one document per page
+pub fn render_svg(puzzles: &[PrintPuzzle], per_page: usize) -> Result<Vec<String>, String> {
+    Ok(layout_pages(puzzles, per_page)?.iter().map(|page| svg_document(page)).collect())
+}
+
+// Print puzzles on A4 pages as a PDF document
+pub fn render_pdf(puzzles: &[PrintPuzzle], per_page: usize) -> Result<Vec<u8>, String> {
+    Ok(pdf_document(&layout_pages(puzzles, per_page)?))
+}
+
+// What is drawn on a page. Coordinates are in mm from the top left corner, gray is from
+// 0 (black) to 1 (white).
+enum Shape {
+    Rect { x: f64, y: f64, width: f64, height: f64, fill: Option<f64>, line_width: Option<f64> },
+    // x is the start of the text, or its middle if it is centered; y is the baseline
+    Text { x: f64, y: f64, size: f64, is_bold: bool, is_centered: bool, gray: f64, text: String },
+}
+
+// Columns and rows of puzzles on a page
+fn booklet_grid(per_page: usize) -> Option<(usize, usize)> {
+    match per_page {
+        1 => Some((1, 1)),
+        2 => Some((1, 2)),
+        4 => Some((2, 2)),
+        6 => Some((2, 3)),
+        _ => None
+    }
+}
+
+// Arrange the puzzles on pages, followed by pages with the solutions
+fn layout_pages(puzzles: &[PrintPuzzle], per_page: usize) -> Result<Vec<Vec<Shape>>, String> {
+    let (columns, rows) = booklet_grid(per_page)
+        .ok_or_else(|| format!("unsupported number of puzzles per page {}.", per_page))?;
+    if puzzles.is_empty() {
+        return Err("no puzzles to print.".into());
+    }
+    let entries = puzzles.iter().map(|puzzle| (puzzle.title.clone(), puzzle, &puzzle.board, &puzzle.pencil_marks[..]))
+        .chain(puzzles.iter().filter_map(|puzzle| puzzle.solution.as_ref()
+            .map(|solution| (format!("Solution: {}", puzzle.title), puzzle, solution, &[][..]))))
+        .collect::<Vec<_>>();
+    let solution_start = puzzles.len();
+    let slot_width = (PAGE_WIDTH - 2.0 * MARGIN - GAP * (columns - 1) as f64) / columns as f64;
+    let slot_height = (PAGE_HEIGHT - 2.0 * MARGIN - GAP * (rows - 1) as f64) / rows as f64;
+
+    let mut pages = vec![];
+    // Solutions start on a new page
+    for part in [&entries[..solution_start], &entries[solution_start..]] {
+        for chunk in part.chunks(per_page) {
+            let mut shapes = vec![];
+            for (k, (title, puzzle, board, pencil_marks)) in chunk.iter().enumerate() {
+                let x = MARGIN + (k % columns) as f64 * (slot_width + GAP);
+                let y = MARGIN + (k / columns) as f64 * (slot_height + GAP);
+                let subtitle = match &puzzle.difficulty {
+                    Some(difficulty) => format!("{} · ID {}", difficulty, puzzle.id),
+                    None => format!("ID {}", puzzle.id),
+                };
+                draw_puzzle(&mut shapes, (x, y, slot_width, slot_height), title, &subtitle, board, pencil_marks);
+            }
+            pages.push(shapes);
+        }
+    }
+    Ok(pages)
+}
+
+// Draw a puzzle with its header into a slot of a page, the board as large as fits
+fn draw_puzzle(shapes: &mut Vec<Shape>, (x, y, width, height): (f64, f64, f64, f64), title: &str,
+        subtitle: &str, board: &Board, pencil_marks: &[Vec<Digit>]) {
+    let title_size = (width.min(height) * 0.05).clamp(3.5, 6.0);
+    let subtitle_size = title_size * 0.7;
+    let header_height = title_size * 1.3 + subtitle_size * 1.6;
+
+    let n = board.size();
+    let ring = if board.clues().is_empty() { 0.0 } else { CLUE_RING };
+    let cell = width.min(height - header_height) / (n as f64 + 2.0 * ring);
+    let left = x + (width - cell * (n as f64 + 2.0 * ring)) / 2.0;
+    let (board_x, board_y) = (left + ring * cell, y + header_height + ring * cell);
+
+    let text = |x, y, size, is_bold, is_centered, gray, text: String| {
+        Shape::Text { x, y, size, is_bold, is_centered, gray, text }
+    };
+    shapes.push(text(left, y + title_size, title_size, true, false, 0.0, title.into()));
+    shapes.push(text(left, y + title_size * 1.3 + subtitle_size * 1.1, subtitle_size, false, false, PLAYER_GRAY,
+        subtitle.into()));
+
+    // Cell i is in row i % n and column i / n on screen
+    let layout = board.layout();
+    let max_digit = board.max_digit();
+    for (i, board_cell) in board.cells().iter().enumerate() {
+        if !layout.is_used(i) {
+            continue;
+        }
+        let (cx, cy) = (board_x + (i / n) as f64 * cell, board_y + (i % n) as f64 * cell);
+        let fill = if board_cell.is_white() { None } else { Some(0.0) };
+        shapes.push(Shape::Rect { x: cx, y: cy, width: cell, height: cell, fill, line_width: Some(CELL_LINE) });
+        // Digits are centered vertically by their height, about 0.7 of the font size
+        if let Some(digit) = board_cell.digit {
+            let gray = match (board_cell.is_white(), board_cell.is_fixed) {
+                (false, _) => 1.0,
+                (true, true) => 0.0,
+                (true, false) => PLAYER_GRAY,
+            };
+            let size = cell * 0.6;
+            shapes.push(text(cx + cell / 2.0, cy + cell / 2.0 + size * 0.35, size, board_cell.is_fixed, true, gray,
+                digit.to_char().to_string()));
+        } else if board_cell.is_white() {
+            // Pencil marks in a small grid per cell, e.g. 3×3 for the digits 1-9
+            let columns = (max_digit as f64).sqrt().ceil() as usize;
+            let rows = max_digit.div_ceil(columns);
+            let pitch = cell * 0.9 / columns.max(rows) as f64;
+            let size = pitch * 0.7;
+            for digit in pencil_marks.get(i).map(|digits| &digits[..]).unwrap_or(&[]) {
+                let k = (digit.get() - 1) as usize;
+                let mx = cx + (cell - pitch * columns as f64) / 2.0 + (k % columns) as f64 * pitch + pitch / 2.0;
+                let my = cy + (cell - pitch * rows as f64) / 2.0 + (k / columns) as f64 * pitch + pitch / 2.0;
+                shapes.push(text(mx, my + size * 0.35, size, false, true, PLAYER_GRAY, digit.to_char().to_string()));
+            }
+        }
+    }
+
+    // Boxes of Sudoku-Str8ts, they span rows × columns cells on screen
+    if let Some((rows, columns)) = box_shape(n).filter(|_| layout.is_single() && board.rules().has_boxes(n)) {
+        for row in (0..n).step_by(rows) {
+            for column in (0..n).step_by(columns) {
+                shapes.push(Shape::Rect { x: board_x + column as f64 * cell, y: board_y + row as f64 * cell,
+                    width: columns as f64 * cell, height: rows as f64 * cell, fill: None, line_width: Some(BOX_LINE) });
+            }
+        }
+    }
+    for grid in &layout.grids {
+        let size = layout.grid_size as f64 * cell;
+        shapes.push(Shape::Rect { x: board_x + grid.column as f64 * cell, y: board_y + grid.row as f64 * cell,
+            width: size, height: size, fill: None, line_width: Some(GRID_LINE) });
+    }
+
+    // Outside clues in the ring around the board
+    for clue in board.clues() {
+        let line = clue.line as f64 + 0.5;
+        let outside = ring / 2.0;
+        let (mx, my) = match clue.side {
+            Side::Top => (line, -outside),
+            Side::Bottom => (line, n as f64 + outside),
+            Side::Left => (-outside, line),
+            Side::Right => (n as f64 + outside, line),
+        };
+        let size = cell * 0.35;
+        shapes.push(text(board_x + mx * cell, board_y + my * cell + size * 0.35, size, false, true, 0.0, clue.label()));
+    }
+}
+
+fn svg_document(shapes: &[Shape]) -> String {
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}mm\" height=\"{}mm\" \
+        viewBox=\"0 0 {} {}\">\n", PAGE_WIDTH, PAGE_HEIGHT, PAGE_WIDTH, PAGE_HEIGHT);
+    svg.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n", PAGE_WIDTH, PAGE_HEIGHT));
+    for shape in shapes {
+        match shape {
+            Shape::Rect { x, y, width, height, fill, line_width } => {
+                let fill = fill.map_or("none".into(), svg_color);
+                let stroke = match line_width {
+                    Some(line_width) => format!(" stroke=\"black\" stroke-width=\"{:.2}\"", line_width),
+                    None => "".into()
+                };
+                svg.push_str(&format!("<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"{}\"{}/>\n",
+                    x, y, width, height, fill, stroke));
+            },
+            Shape::Text { x, y, size, is_bold, is_centered, gray, text } => {
+                svg.push_str(&format!("<text x=\"{:.2}\" y=\"{:.2}\" font-family=\"Helvetica, Arial, sans-serif\" \
+                    font-size=\"{:.2}\" font-weight=\"{}\" text-anchor=\"{}\" fill=\"{}\">{}</text>\n",
+                    x, y, size, if *is_bold { "bold" } else { "normal" }, if *is_centered { "middle" } else { "start" },
+                    svg_color(*gray), escape_xml(text)));
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn svg_color(gray: f64) -> String {
+    let value = (gray * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", value, value, value)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// PDF uses points (1/72 inch) from the bottom left corner
+fn pt(mm: f64) -> f64 {
+    mm * 72.0 / 25.4
+}
+
+// The fonts of the PDF: standard fonts, which viewers provide
+const PDF_FONTS: [&str; 3] = ["Helvetica", "Helvetica-Bold", "Symbol"];
+
+// A PDF of pages of the given shapes. The objects are the catalog, the page tree, the fonts
+// and a page and its content per page; content is not compressed.
+fn pdf_document(pages: &[Vec<Shape>]) -> Vec<u8> {
+    let first_page = 3 + PDF_FONTS.len();
+    let page_ids = (0..pages.len()).map(|k| first_page + 2 * k).collect::<Vec<_>>();
+    let mut objects: Vec<Vec<u8>> = vec![];
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    let kids = page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" ");
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()).into_bytes());
+    for font in &PDF_FONTS {
+        // Symbol has its own encoding, the text fonts use the Windows one
+        let encoding = if *font == "Symbol" { "" } else { " /Encoding /WinAnsiEncoding" };
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{}{} >>", font, encoding).into_bytes());
+    }
+    let fonts = (0..PDF_FONTS.len()).map(|k| format!("/F{} {} 0 R", k + 1, k + 3)).collect::<Vec<_>>().join(" ");
+    for (page, id) in pages.iter().zip(&page_ids) {
+        objects.push(format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << {} >> >> \
+            /Contents {} 0 R >>", pt(PAGE_WIDTH), pt(PAGE_HEIGHT), fonts, id + 1).into_bytes());
+        let content = pdf_content(page);
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = vec![];
+    for (k, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", k + 1).into_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).into_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).into_bytes());
+    }
+    pdf.extend(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).into_bytes());
+    pdf
+}
+
+// The drawing operators of a page
+fn pdf_content(shapes: &[Shape]) -> Vec<u8> {
+    let mut content = vec![];
+    for shape in shapes {
+        match shape {
+            Shape::Rect { x, y, width, height, fill, line_width } => {
+                let rect = format!("{:.2} {:.2} {:.2} {:.2} re", pt(*x), pt(PAGE_HEIGHT - y - height), pt(*width), pt(*height));
+                let operator = match (fill, line_width) {
+                    (Some(_), Some(_)) => "B",
+                    (Some(_), None) => "f",
+                    (None, Some(_)) => "S",
+                    (None, None) => continue,
+                };
+                let fill = fill.map_or("".into(), |gray| format!("{:.2} g ", gray));
+                let line_width = line_width.map_or("".into(), |width| format!("{:.2} w 0 G ", pt(width)));
+                content.extend(format!("{}{}{} {}\n", fill, line_width, rect, operator).into_bytes());
+            },
+            Shape::Text { x, y, size, is_bold, is_centered, gray, text } => {
+                let runs = pdf_text_runs(text, *is_bold);
+                let width = runs.iter().map(|(_, _, width)| width).sum::<f64>() * size / 1000.0;
+                let start = if *is_centered { x - width / 2.0 } else { *x };
+                content.extend(format!("{:.2} g BT {:.2} {:.2} Td", gray, pt(start), pt(PAGE_HEIGHT - y)).into_bytes());
+                for (font, bytes, _) in runs {
+                    content.extend(format!(" /F{} {:.2} Tf (", font, pt(*size)).into_bytes());
+                    for byte in bytes {
+                        match byte {
+                            b'(' | b')' | b'\\' => content.extend([b'\\', byte]),
+                            32..=126 => content.push(byte),
+                            _ => content.extend(format!("\\{:03o}", byte).into_bytes()),
+                        }
+                    }
+                    content.extend(b") Tj");
+                }
+                content.extend(b" ET\n");
+            }
+        }
+    }
+    content
+}
+
+// Split a text into runs of one font: its number, the encoded characters and their width in
+// 1/1000 of the font size. ≥ and ≤ are taken from the Symbol font, characters that the fonts
+// don't have become '?'.
+fn pdf_text_runs(text: &str, is_bold: bool) -> Vec<(usize, Vec<u8>, f64)> {
+    let text_font = if is_bold { 2 } else { 1 };
+    let mut runs: Vec<(usize, Vec<u8>, f64)> = vec![];
+    for c in text.chars() {
+        let (font, byte) = match c {
+            '≥' => (3, 0xb3),
+            '≤' => (3, 0xa3),
+            '–' => (text_font, 0x96),
+            '—' => (text_font, 0x97),
+            ' '..='~' | '\u{a0}'..='ÿ' => (text_font, c as u32 as u8),
+            _ => (text_font, b'?'),
+        };
+        let width = char_width(c, is_bold);
+        match runs.last_mut() {
+            Some((last_font, bytes, run_width)) if *last_font == font => {
+                bytes.push(byte);
+                *run_width += width;
+            },
+            _ => runs.push((font, vec![byte], width)),
+        }
+    }
+    runs
+}
+
+// Widths of characters in Helvetica, in 1/1000 of the font size. Only centered texts (digits
+// and clues) need them, other characters get an average width.
+fn char_width(c: char, is_bold: bool) -> f64 {
+    match c {
+        '0'..='9' => 556.0,
+        '≥' | '≤' => 549.0,
+        'A' | 'B' => if is_bold { 722.0 } else { 667.0 },
+        'C' => 722.0,
+        ' ' => 278.0,
+        _ => 556.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::parse_text;
+    use crate::layout::Layout;
+
+    fn puzzles(count: usize) -> Vec<PrintPuzzle> {
+        let board = parse_text("clue: left 1 sum 15\nc...a.\n.2.##.\n#5.##.\n#..#3.\n...##6\n.6#1.#\n").unwrap();
+        let solution = parse_text("c465a2\n123##5\n#54##3\n#12#34\n435##6\n56#12#\n").unwrap();
+        let mut pencil_marks = vec![vec![]; board.len()];
+        pencil_marks[1] = vec![Digit::new(4).unwrap(), Digit::new(6).unwrap()];
+        let mut puzzles = (0..count)
+            .map(|k| PrintPuzzle {
+                board: board.clone(),
+                pencil_marks: pencil_marks.clone(),
+                title: format!("Puzzle <{}> & \"more\"", k + 1),
+                difficulty: Some("Easy".into()),
+                id: "0123abcd".into(),
+                solution: Some(solution.clone()),
+            })
+            .collect::<Vec<_>>();
+        puzzles.push(PrintPuzzle {
+            board: Board::empty_with_layout(Layout::samurai()),
+            pencil_marks: vec![],
+            title: "Samurai".into(),
+            difficulty: None,
+            id: "89abcdef".into(),
+            solution: None,
+        });
+        puzzles
+    }
+
+    // Check that the tags of an SVG document are nested correctly and the text has no markup
+    fn assert_well_formed(svg: &str) {
+        let mut open_tags = vec![];
+        let mut rest = svg;
+        while let Some(start) = rest.find('<') {
+            let text = &rest[..start];
+            assert!(!text.contains('>'), "{}", text);
+            assert!(text.match_indices('&').all(|(k, _)| ["&amp;", "&lt;", "&gt;", "&quot;"].iter()
+                .any(|entity| text[k..].starts_with(entity))), "{}", text);
+            let end = start + rest[start..].find('>').expect("unclosed tag");
+            let tag = &rest[start + 1..end];
+            assert!(!tag.contains('<'), "{}", tag);
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(open_tags.pop(), Some(name));
+            } else if !tag.ends_with('/') {
+                open_tags.push(tag.split_whitespace().next().unwrap());
+            }
+            assert_eq!(tag.matches('"').count() % 2, 0, "{}", tag);
+            rest = &rest[end + 1..];
+        }
+        assert!(open_tags.is_empty(), "{:?}", open_tags);
+        assert_eq!(rest.trim(), "");
+    }
+
+    #[test]
+    fn svg_pages() {
+        // 5 puzzles on 2 pages, the 4 solutions on another page
+        let pages = render_svg(&puzzles(4), 4).unwrap();
+        assert_eq!(pages.len(), 3);
+        for page in &pages {
+            assert!(page.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+            assert_well_formed(page);
+        }
+        assert!(pages[0].contains("Puzzle &lt;1&gt; &amp; &quot;more&quot;"));
+        assert!(pages[1].contains("Samurai"));
+        assert!(pages[2].contains("Solution: Puzzle"));
+    }
+
+    fn find(data: &[u8], pattern: &str) -> Option<usize> {
+        data.windows(pattern.len()).position(|window| window == pattern.as_bytes())
+    }
+
+    // The number at the start of the data
+    fn number(data: &[u8]) -> usize {
+        let digits = data.iter().take_while(|c| c.is_ascii_digit()).count();
+        std::str::from_utf8(&data[..digits]).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn pdf_document() {
+        let pdf = render_pdf(&puzzles(2), 2).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+
+        // startxref points to the table, whose entries point to the objects
+        let startxref = pdf.windows(10).rposition(|window| window == b"startxref\n").unwrap();
+        let xref = number(&pdf[startxref + 10..]);
+        assert!(pdf[xref..].starts_with(b"xref\n0 "));
+        let count = number(&pdf[xref + 7..]);
+        // Catalog, page tree, 3 fonts, and a page and its contents for each of 2 + 1 pages
+        assert_eq!(count, 1 + 2 + 3 + 2 * 3);
+        let entries = xref + find(&pdf[xref..], "65535 f \n").unwrap() + 9;
+        for k in 1..count {
+            let offset = number(&pdf[entries + 20 * (k - 1)..]);
+            assert!(pdf[offset..].starts_with(format!("{} 0 obj\n", k).as_bytes()), "object {}", k);
+        }
+        assert!(find(&pdf, &format!("trailer\n<< /Size {} /Root 1 0 R >>", count)).is_some());
+
+        // The lengths of the content streams are right
+        let mut rest = &pdf[..];
+        while let Some(start) = find(rest, "<< /Length ") {
+            let length = number(&rest[start + 11..]);
+            let stream = start + find(&rest[start..], "stream\n").unwrap() + 7;
+            assert!(rest[stream + length..].starts_with(b"\nendstream"));
+            rest = &rest[stream + length..];
+        }
+    }
+
+    #[test]
+    fn errors() {
+        assert!(render_svg(&[], 1).is_err());
+        assert!(render_pdf(&puzzles(1), 3).is_err());
+    }
+}
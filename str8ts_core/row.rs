@@ -0,0 +1,54 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::constraints::Unit;
+use super::store::CellStore;
+
+// Represents a row/column and its straights. The rules about them are in constraints.rs.
+pub struct Row {
+    unit: Unit, // the row/column (or box) as seen on screen
+    row_cells: Vec<usize>, // indices of cells in row/column
+    straights: Vec<Vec<usize>>, // straights, stored as index vectors
+}
+
+impl Row {
+    // Create new row, recognize it straights
+    pub fn new<S: CellStore + ?Sized>(unit: Unit, row_cells: Vec<usize>, board: &S) -> Row {
+        let straights = row_cells.iter()
+            .map(|&i| (i, board.is_white(i))).collect::<Vec<(usize, bool)>>()
+            .split(|(_, is_white)| !is_white)
+            .filter(|&slice| !slice.is_empty())
+            .map(|slice| slice.iter().map(|(i, _)| *i).collect()).collect();
+        Row { unit, row_cells, straights }
+    }
+
+    // Create a box of Sudoku-Str8ts: no repeated digits, but no straights either
+    pub fn new_box(unit: Unit, box_cells: Vec<usize>) -> Row {
+        Row { unit, row_cells: box_cells, straights: vec![] }
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    // Indices of the cells in the row/column
+    pub fn cells(&self) -> &Vec<usize> {
+        &self.row_cells
+    }
+
+    // Find the straight a cell is in (None for black cells)
+    pub fn straight_of(&self, cell_index: usize) -> Option<&Vec<usize>> {
+        self.straights.iter().find(|s| s.contains(&cell_index))
+    }
+
+    // The straights of the row/column, as cell indices
+    pub fn straights(&self) -> &Vec<Vec<usize>> {
+        &self.straights
+    }
+}
@@ -0,0 +1,72 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use serde::{Deserialize, Serialize};
+
+// Rules of a puzzle in addition to the classic Str8ts rules. Rules a board size
+// does not support (e.g. boxes on a 5×5 board) are ignored.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    pub boxes: bool, // Sudoku-Str8ts: no digit is repeated in a box
+}
+
+impl RuleSet {
+    pub fn classic() -> RuleSet {
+        RuleSet::default()
+    }
+
+    pub fn sudoku() -> RuleSet {
+        RuleSet { boxes: true }
+    }
+
+    pub fn from_name(name: &str) -> Option<RuleSet> {
+        match name {
+            "classic" => Some(RuleSet::classic()),
+            "sudoku" => Some(RuleSet::sudoku()),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        if self.boxes { "sudoku" } else { "classic" }
+    }
+
+    // Check if the board size supports boxes, i.e. if boxes are in effect
+    pub fn has_boxes(&self, size: usize) -> bool {
+        self.boxes && box_shape(size).is_some()
+    }
+}
+
+// Number of rows and columns (on screen) of a box, None for sizes without boxes
+pub fn box_shape(size: usize) -> Option<(usize, usize)> {
+    match size {
+        4 => Some((2, 2)),
+        6 => Some((2, 3)),
+        8 => Some((2, 4)),
+        9 => Some((3, 3)),
+        12 => Some((3, 4)),
+        _ => None
+    }
+}
+
+// Index of the box a cell is in. On screen, rows run along pos_x = i % size.
+pub fn box_index(cell_index: usize, size: usize, (rows, columns): (usize, usize)) -> usize {
+    let (pos_x, pos_y) = (cell_index % size, cell_index / size);
+    (pos_x / rows) * (size / columns) + pos_y / columns
+}
+
+// Cell indices of all boxes, ordered by box_index
+pub fn box_cells(size: usize, shape: (usize, usize)) -> Vec<Vec<usize>> {
+    let mut boxes = vec![vec![]; size];
+    for i in 0..size * size {
+        boxes[box_index(i, size, shape)].push(i);
+    }
+    boxes
+}
@@ -0,0 +1,374 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{Board, Digit};
+use super::constraints::{Constraints, Unit};
+use super::layout::Layout;
+use super::rules::{box_cells, box_shape, RuleSet};
+use super::store::{BitBoard, CellStore};
+use super::row::Row;
+
+// Represents whether the game has no/one/multiple solutions
+// (including one solution in the latter cases)
+pub enum Str8tsSolution {
+    None,
+    Unique(Board),
+    Multiple(Board)
+}
+
+// Recognize row/column straights structure. For each grid, the columns on screen come first
+// (on a single grid, the cells size*k..size*(k+1)), then the rows. The boxes follow if the rules
+// have them; boxes are only supported on single grids.
+pub fn compute_rows_columns<S: CellStore + ?Sized>(board: &S, layout: &Layout, rules: RuleSet) -> Vec<Row> {
+    let n = layout.grid_size;
+    let mut rows_columns = vec![];
+    for (k, grid) in layout.grids.iter().enumerate() {
+        for column in 0..n {
+            let indices = (0..n).map(|row| layout.grid_cell(k, row, column)).collect::<Vec<_>>();
+            rows_columns.push(Row::new(Unit::Column(grid.column + column), indices, board));
+        }
+        for row in 0..n {
+            let indices = (0..n).map(|column| layout.grid_cell(k, row, column)).collect::<Vec<_>>();
+            rows_columns.push(Row::new(Unit::Row(grid.row + row), indices, board));
+        }
+    }
+    if let Some(shape) = box_shape(n).filter(|_| layout.is_single() && rules.has_boxes(n)) {
+        rows_columns.extend(box_cells(n, shape).into_iter().enumerate()
+            .map(|(k, cells)| Row::new_box(Unit::Box(k), cells)));
+    }
+    rows_columns
+}
+
+// Result of a backtracking search: the solutions found (at most the requested number),
+// the number of values tried and whether the search space was fully explored
+pub struct SearchResult {
+    pub solutions: Vec<Board>,
+    pub nodes: usize,
+    pub is_exhaustive: bool,
+}
+
+// Solve puzzle via backtracking (can take a long time). Returns if the puzzle
+// has no solution, a unique solution or multiple solutions.
+pub fn solve_backtrack(board: Board) -> Str8tsSolution {
+    solve_backtrack_cancellable(board, &|_| false).unwrap()
+}
+
+// Like solve_backtrack, but returns None if the search is cancelled
+pub fn solve_backtrack_cancellable(board: Board, is_cancelled: &dyn Fn(usize) -> bool)
+        -> Option<Str8tsSolution> {
+    let result = search_solutions(board, 2, is_cancelled);
+    if !result.is_exhaustive && result.solutions.len() < 2 {
+        return None;
+    }
+    let mut solutions = result.solutions.into_iter();
+    Some(match (solutions.next(), solutions.next()) {
+        (None, _) => Str8tsSolution::None,
+        (Some(solution), None) => Str8tsSolution::Unique(solution),
+        (Some(solution), Some(_)) => Str8tsSolution::Multiple(solution),
+    })
+}
+
+// Backtracking search for up to max_solutions solutions. is_cancelled is called regularly
+// with the number of values tried so far, a cancelled search returns what it has found.
+pub fn search_solutions(board: Board, max_solutions: usize, is_cancelled: &dyn Fn(usize) -> bool)
+        -> SearchResult {
+    search(BitBoard::from_store(&board), &board, max_solutions, is_cancelled, &mut |_| {})
+}
+
+// A step of the solver, for replaying how a puzzle is solved
+#[derive(Clone)]
+pub enum SolverStep {
+    Place { cell: usize, digit: Digit },
+    Eliminate { cell: usize, digits: Vec<Digit> }, // candidates that are ruled out
+    Backtrack { cell: usize }, // a placed digit is removed again
+}
+
+// Record the steps of solving a puzzle: first the cells with only one possible digit
+// are filled, then the rest is found by backtracking. A cancelled recording is incomplete.
+pub fn record_solver_steps(board: Board, is_cancelled: &dyn Fn(usize) -> bool) -> Vec<SolverStep> {
+    let mut cells = BitBoard::from_store(&board);
+    let mut steps = vec![];
+    fill_single_values(&mut cells, &Constraints::new(&board), &mut |step| steps.push(step));
+    search(cells, &board, 1, is_cancelled, &mut |step| steps.push(step));
+    steps
+}
+
+// Repeatedly fill in cells that have only one possible digit
+fn fill_single_values<S: CellStore>(board: &mut S, constraints: &Constraints, on_step: &mut dyn FnMut(SolverStep)) {
+    let mut candidates = vec![Digit::all(constraints.max_digit()).collect::<Vec<_>>(); board.len()];
+    loop {
+        let mut has_changed = false;
+        for (i, cell_candidates) in candidates.iter_mut().enumerate() {
+            if !board.is_white(i) || board.digit(i).is_some() {
+                continue;
+            }
+            let possible_values = constraints.candidates(i, board);
+            let eliminated = cell_candidates.iter().cloned()
+                .filter(|digit| !possible_values.contains(digit))
+                .collect::<Vec<_>>();
+            if !eliminated.is_empty() {
+                on_step(SolverStep::Eliminate { cell: i, digits: eliminated });
+                *cell_candidates = possible_values.clone();
+                has_changed = true;
+            }
+            if possible_values.len() == 1 {
+                board.set_digit(i, Some(possible_values[0]));
+                on_step(SolverStep::Place { cell: i, digit: possible_values[0] });
+                has_changed = true;
+            }
+        }
+        if !has_changed {
+            break;
+        }
+    }
+}
+
+// Backtracking search on a copy of the puzzle's cells, reporting each placed and removed digit
+fn search<S: CellStore>(mut board: S, puzzle: &Board, max_solutions: usize,
+        is_cancelled: &dyn Fn(usize) -> bool, on_step: &mut dyn FnMut(SolverStep)) -> SearchResult {
+    // The constraints only depend on the black and white cells, which the search doesn't change
+    let constraints = Constraints::new(puzzle);
+
+    // Backtracking stacks: if i > j, cell j either had a digit beforehand, is black, or
+    // has the digit possible_values_stack[i][indices_stack[i]]
+    let mut indices_stack = vec![];
+    let mut possible_values_stack: Vec<Vec<Digit>> = vec![];
+    let mut i = 0;
+    let mut nodes = 0;
+    let mut is_exhaustive = true;
+
+    // Continue until enough solutions are found or the backtracking terminates
+    let mut found_solutions = vec![];
+    'search: while found_solutions.len() < max_solutions {
+        while i < board.len() {
+            // Skip new cells where no digit is needed (already had a digit or black)
+            if (!board.is_white(i) || board.digit(i).is_some()) && i >= possible_values_stack.len() {
+                possible_values_stack.push(vec![]);
+                indices_stack.push(0);
+                i += 1;
+                continue;
+            }
+
+            // If no possible digits are computed yet, compute and put on stack
+            if i >= possible_values_stack.len() {
+                let possible_values = constraints.candidates(i, &board);
+                possible_values_stack.push(possible_values);
+                indices_stack.push(0);
+            }
+            let possible_values = &possible_values_stack[i];
+
+            // If not all possible digits have been exhausted, try the next one
+            if indices_stack[i] < possible_values.len() {
+                let digit = possible_values[indices_stack[i]];
+                board.set_digit(i, Some(digit));
+                on_step(SolverStep::Place { cell: i, digit });
+                indices_stack[i] += 1;
+                i += 1;
+                nodes += 1;
+                if nodes % 256 == 0 && is_cancelled(nodes) {
+                    is_exhaustive = false;
+                    break 'search;
+                }
+            }
+            // Otherwise, give up this cell and backtrack
+            else {
+                let number_of_possibilities = possible_values_stack.pop().unwrap().len();
+                indices_stack.pop();
+                if number_of_possibilities > 0 {
+                    board.set_digit(i, None);
+                    on_step(SolverStep::Backtrack { cell: i });
+                }
+                i = if i > 0 { i - 1 } else { break; }
+            }
+        }
+        // If the inner loop finishes and i != 0, a solution has been found
+        if i != 0 {
+            found_solutions.push(solution_of(puzzle, &board));
+            i -= 1;
+        }
+        // If i = 0, no (further) solutions exist
+        else {
+            break;
+        }
+    }
+    if found_solutions.len() >= max_solutions {
+        is_exhaustive = false;
+    }
+
+    SearchResult { solutions: found_solutions, nodes, is_exhaustive }
+}
+
+// The puzzle with the digits of a filled copy of its cells
+fn solution_of<S: CellStore>(puzzle: &Board, cells: &S) -> Board {
+    let mut solution = puzzle.clone();
+    for i in 0..solution.len() {
+        solution.set_digit(i, cells.digit(i));
+    }
+    solution
+}
+
+// Estimated difficulty of a puzzle with a unique solution
+#[derive(Clone, Copy, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Extreme
+}
+
+// Puzzles that can be filled by repeatedly entering the only possible digit of a cell
+// are easy, otherwise the effort of the backtracking search decides
+pub fn estimate_difficulty(mut board: Board, search_nodes: usize) -> Difficulty {
+    let constraints = Constraints::new(&board);
+    fill_single_values(&mut board, &constraints, &mut |_| {});
+
+    if board.is_complete() {
+        Difficulty::Easy
+    } else if search_nodes < 1000 {
+        Difficulty::Medium
+    } else if search_nodes < 20000 {
+        Difficulty::Hard
+    } else {
+        Difficulty::Extreme
+    }
+}
+
+// Result of analyzing a puzzle while designing it
+pub struct Analysis {
+    // Number of solutions found, at most the requested maximum
+    pub solution_count: usize,
+    // Whether there may be more solutions than solution_count
+    pub has_more_solutions: bool,
+    // Only for puzzles with a unique solution
+    pub difficulty: Option<Difficulty>,
+    // White cells with different digits in the first two solutions
+    pub differing_cells: Vec<usize>,
+}
+
+// Count the solutions of a puzzle (up to max_solutions) and estimate its difficulty.
+// Returns None if the analysis was cancelled.
+pub fn analyze_puzzle(board: Board, max_solutions: usize, is_cancelled: &dyn Fn(usize) -> bool)
+        -> Option<Analysis> {
+    let result = search_solutions(board.clone(), max_solutions, is_cancelled);
+    if !result.is_exhaustive && result.solutions.len() < max_solutions {
+        return None;
+    }
+
+    let solution_count = result.solutions.len();
+    let difficulty = if solution_count == 1 && result.is_exhaustive {
+        Some(estimate_difficulty(board, result.nodes))
+    } else {
+        None
+    };
+    let differing_cells = match &result.solutions[..] {
+        [first, second, ..] => (0..first.len())
+            .filter(|&i| first[i].is_white() && first[i].digit != second[i].digit)
+            .collect(),
+        _ => vec![]
+    };
+
+    Some(Analysis {
+        solution_count,
+        has_more_solutions: !result.is_exhaustive,
+        difficulty,
+        differing_cells
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{format_text, parse_text};
+
+    const PUZZLE: &str = "c...a.\n.2.##.\n#5.##.\n#..#3.\n...##6\n.6#1.#\n";
+    const SOLUTION: &str = "c465a2\n123##5\n#54##3\n#12#34\n435##6\n56#12#\n";
+    // Unique only with the boxes of Sudoku-Str8ts
+    const SUDOKU_PUZZLE: &str = "rules: sudoku\n....5#\n#..1..\n.....3\n.#..##\n..#...\n..###.\n";
+    const SUDOKU_SOLUTION: &str = "rules: sudoku\n12435#\n#35142\n412563\n5#34##\n65#234\n34###5\n";
+
+    fn board(text: &str) -> Board {
+        parse_text(text).unwrap()
+    }
+
+    #[test]
+    fn unique_solution() {
+        match solve_backtrack(board(PUZZLE)) {
+            Str8tsSolution::Unique(solution) => assert_eq!(format_text(&solution), SOLUTION),
+            _ => panic!("expected a unique solution")
+        }
+    }
+
+    #[test]
+    fn boxes_make_the_solution_unique() {
+        match solve_backtrack(board(SUDOKU_PUZZLE)) {
+            Str8tsSolution::Unique(solution) => assert_eq!(format_text(&solution), SUDOKU_SOLUTION),
+            _ => panic!("expected a unique solution")
+        }
+        let classic = board(SUDOKU_PUZZLE).with_rules(RuleSet::classic());
+        assert!(matches!(solve_backtrack(classic), Str8tsSolution::Multiple(_)));
+    }
+
+    #[test]
+    fn no_solution() {
+        // The straight in the first row needs a 2, the one in the third column has it already
+        let puzzle = board("#1.#\n##2#\n####\n####\n");
+        assert!(matches!(solve_backtrack(puzzle), Str8tsSolution::None));
+    }
+
+    #[test]
+    fn count_solutions() {
+        let result = search_solutions(board(PUZZLE), 10, &|_| false);
+        assert_eq!(result.solutions.len(), 1);
+        assert!(result.is_exhaustive);
+
+        let result = search_solutions(Board::empty(4), 3, &|_| false);
+        assert_eq!(result.solutions.len(), 3);
+        assert!(!result.is_exhaustive);
+    }
+
+    #[test]
+    fn cancelled_search() {
+        let result = search_solutions(Board::empty(9), usize::MAX, &|_| true);
+        assert!(!result.is_exhaustive);
+        assert_eq!(result.nodes, 256);
+        // A node budget stops the search long before all solutions of an empty board are found
+        let is_cancelled = |nodes: usize| nodes >= 1000;
+        assert!(analyze_puzzle(Board::empty(9), usize::MAX, &is_cancelled).is_none());
+        // Searches that finish within the budget are not cancelled
+        assert!(solve_backtrack_cancellable(board(PUZZLE), &is_cancelled).is_some());
+    }
+
+    #[test]
+    fn analysis() {
+        let analysis = analyze_puzzle(board(PUZZLE), 2, &|_| false).unwrap();
+        assert_eq!(analysis.solution_count, 1);
+        assert!(!analysis.has_more_solutions);
+        assert!(analysis.difficulty == Some(Difficulty::Easy));
+
+        let analysis = analyze_puzzle(board(SUDOKU_PUZZLE).with_rules(RuleSet::classic()), 2, &|_| false).unwrap();
+        assert_eq!(analysis.solution_count, 2);
+        assert!(analysis.has_more_solutions);
+        assert!(analysis.difficulty.is_none());
+        assert!(!analysis.differing_cells.is_empty());
+    }
+
+    #[test]
+    fn replaying_recorded_steps_solves_the_puzzle() {
+        let puzzle = board(SUDOKU_PUZZLE);
+        let mut replayed = puzzle.clone();
+        for step in record_solver_steps(puzzle, &|_| false) {
+            match step {
+                SolverStep::Place { cell, digit } => replayed[cell].digit = Some(digit),
+                SolverStep::Backtrack { cell } => replayed[cell].digit = None,
+                SolverStep::Eliminate { cell, .. } => assert!(replayed[cell].digit.is_none()),
+            }
+        }
+        assert_eq!(format_text(&replayed), SUDOKU_SOLUTION);
+    }
+}
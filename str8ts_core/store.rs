@@ -0,0 +1,186 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::{area_size_of_cell_count, Board, BoardCell, CellKind, Digit};
+use super::layout::MAX_AREA_SIZE;
+
+// Storage of the cells of a board. The rules and the solver work on any storage:
+// the engine's Board, a plain Vec<BoardCell>, the UI model (the app implements the trait
+// for its VecModel<Cell> in main.rs, see UiCells) or the compact BitBoard used while searching.
+pub trait CellStore {
+    fn len(&self) -> usize;
+    fn get(&self, i: usize) -> BoardCell;
+    fn set(&mut self, i: usize, cell: BoardCell);
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Number of cells per row/column
+    fn size(&self) -> usize {
+        area_size_of_cell_count(self.len()).unwrap_or_else(|| panic!("A board can't have {} cells.", self.len()))
+    }
+
+    // Borrow all cells at once, if the storage keeps them as BoardCells
+    fn as_slice(&self) -> Option<&[BoardCell]> {
+        None
+    }
+
+    // Iterate over all cells in index order
+    fn iter_cells(&self) -> Cells<'_, Self> where Self: Sized {
+        Cells { store: self, i: 0 }
+    }
+
+    fn digit(&self, i: usize) -> Option<Digit> {
+        self.get(i).digit
+    }
+
+    fn is_white(&self, i: usize) -> bool {
+        self.get(i).is_white()
+    }
+
+    fn set_digit(&mut self, i: usize, digit: Option<Digit>) {
+        let mut cell = self.get(i);
+        cell.digit = digit;
+        self.set(i, cell);
+    }
+}
+
+// Iterator over the cells of a storage
+pub struct Cells<'a, S: CellStore> {
+    store: &'a S,
+    i: usize,
+}
+
+impl<'a, S: CellStore> Iterator for Cells<'a, S> {
+    type Item = BoardCell;
+
+    fn next(&mut self) -> Option<BoardCell> {
+        if self.i < self.store.len() {
+            self.i += 1;
+            Some(self.store.get(self.i - 1))
+        } else {
+            None
+        }
+    }
+}
+
+impl CellStore for Vec<BoardCell> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn get(&self, i: usize) -> BoardCell {
+        self[i]
+    }
+
+    fn set(&mut self, i: usize, cell: BoardCell) {
+        self[i] = cell;
+    }
+
+    fn as_slice(&self) -> Option<&[BoardCell]> {
+        Some(self)
+    }
+}
+
+impl CellStore for Board {
+    fn len(&self) -> usize {
+        Board::len(self)
+    }
+
+    fn size(&self) -> usize {
+        Board::size(self)
+    }
+
+    fn get(&self, i: usize) -> BoardCell {
+        self[i]
+    }
+
+    fn set(&mut self, i: usize, cell: BoardCell) {
+        self[i] = cell;
+    }
+
+    fn as_slice(&self) -> Option<&[BoardCell]> {
+        Some(self.cells())
+    }
+}
+
+const MAX_CELLS: usize = MAX_AREA_SIZE * MAX_AREA_SIZE;
+const MASK_WORDS: usize = MAX_CELLS.div_ceil(64);
+const DIGIT_WORDS: usize = (4 * MAX_CELLS).div_ceil(64);
+
+// Compact board: one bit per cell for white and fixed, four bits per cell for the digit
+// (0 for empty). It is Copy, so the solver can clone it cheaply while backtracking.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BitBoard {
+    size: u8,
+    white: [u64; MASK_WORDS],
+    fixed: [u64; MASK_WORDS],
+    digits: [u64; DIGIT_WORDS],
+}
+
+impl BitBoard {
+    // Copy the cells of another storage
+    pub fn from_store<S: CellStore>(store: &S) -> BitBoard {
+        let size = store.size() as u8;
+        let mut board = BitBoard { size, white: [0; MASK_WORDS], fixed: [0; MASK_WORDS], digits: [0; DIGIT_WORDS] };
+        for (i, cell) in store.iter_cells().enumerate() {
+            board.set(i, cell);
+        }
+        board
+    }
+
+    fn bit(mask: &[u64], i: usize) -> bool {
+        mask[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn set_bit(mask: &mut [u64], i: usize, value: bool) {
+        if value {
+            mask[i / 64] |= 1 << (i % 64);
+        } else {
+            mask[i / 64] &= !(1 << (i % 64));
+        }
+    }
+}
+
+impl CellStore for BitBoard {
+    fn len(&self) -> usize {
+        self.size() * self.size()
+    }
+
+    fn size(&self) -> usize {
+        self.size as usize
+    }
+
+    fn get(&self, i: usize) -> BoardCell {
+        let kind = if self.is_white(i) { CellKind::White } else { CellKind::Black };
+        BoardCell::new(kind, self.digit(i), BitBoard::bit(&self.fixed, i))
+    }
+
+    fn set(&mut self, i: usize, cell: BoardCell) {
+        BitBoard::set_bit(&mut self.white, i, cell.is_white());
+        BitBoard::set_bit(&mut self.fixed, i, cell.is_fixed);
+        self.set_digit(i, cell.digit);
+    }
+
+    fn digit(&self, i: usize) -> Option<Digit> {
+        let (word, shift) = (4 * i / 64, 4 * i % 64);
+        Digit::new(((self.digits[word] >> shift) & 0xf) as i32)
+    }
+
+    fn is_white(&self, i: usize) -> bool {
+        BitBoard::bit(&self.white, i)
+    }
+
+    fn set_digit(&mut self, i: usize, digit: Option<Digit>) {
+        let (word, shift) = (4 * i / 64, 4 * i % 64);
+        let value = digit.map_or(0, Digit::get) as u64;
+        self.digits[word] = (self.digits[word] & !(0xf << shift)) | (value << shift);
+    }
+}
@@ -0,0 +1,224 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::thumbnail::BoardColors;
+use image::Rgba;
+
+// A built-in theme of the app. The fields are the ones of the Theme struct in the .60 file,
+// colors as 0xrrggbb and lengths in px; all built-in themes use the default font. The app turns
+// them into Themes, thumbnails are drawn in their colors.
+pub struct BuiltinTheme {
+    pub name: &'static str,
+    pub window_background_color: u32,
+    pub game_background_color: u32,
+    pub game_border: f32,
+    pub game_border_color: u32,
+    pub game_radius: f32,
+    pub game_text_color: u32,
+    pub game_highlight_color: u32,
+    pub cell_border: f32,
+    pub cell_border_color: u32,
+    pub cell_background_color_white: u32,
+    pub cell_background_color_black: u32,
+    pub cell_background_color_solved: u32,
+    pub cell_background_color_editing_white: u32,
+    pub cell_background_color_editing_black: u32,
+    pub cell_background_color_wrong: u32,
+    pub cell_background_color_peer: u32,
+    pub cell_background_color_compartment: u32,
+    pub cell_background_color_same_value: u32,
+    pub cell_background_color_ambiguous: u32,
+    pub cell_text_color_black: u32,
+    pub cell_text_color_white: u32,
+    pub cell_text_color_wrong: u32,
+    pub cell_text_color_highlighted_small_value: u32,
+    pub marking_colors: [u32; 6],
+    pub button_text_color: u32,
+    pub button_hover_color: u32,
+    pub button_activated_color: u32,
+    pub cell_text_weight_fixed_value: i32,
+    pub cell_text_weight_nonfixed_value: i32,
+    pub cell_radius: f32,
+    pub cell_spacing: f32, // ratio of the cell size
+    pub cells_outer_border: f32,
+}
+
+// In the order of the theme picker; the first one is the default
+pub static BUILTIN_THEMES: [BuiltinTheme; 4] = [
+    BuiltinTheme {
+        name: "SIMPLE",
+        window_background_color: 0xffffff,
+        game_background_color: 0xffffff,
+        game_border: 2.0,
+        game_border_color: 0x000000,
+        game_radius: 2.0,
+        game_text_color: 0x858585,
+        game_highlight_color: 0x1d6aaa,
+        cell_border: 1.0,
+        cell_border_color: 0x000000,
+        cell_background_color_white: 0xffffff,
+        cell_background_color_black: 0x000000,
+        cell_background_color_solved: 0x99fb99,
+        cell_background_color_editing_white: 0xf0f2a4,
+        cell_background_color_editing_black: 0x535339,
+        cell_background_color_wrong: 0xff5c5c,
+        cell_background_color_peer: 0xf2f6fa,
+        cell_background_color_compartment: 0xe2ecf6,
+        cell_background_color_same_value: 0xc4daee,
+        cell_background_color_ambiguous: 0xffd8a8,
+        cell_text_color_black: 0xffffff,
+        cell_text_color_white: 0x000000,
+        cell_text_color_wrong: 0xdf0000,
+        cell_text_color_highlighted_small_value: 0x1d6aaa,
+        marking_colors: [0xffd37a, 0xa8e6a1, 0x9fd0ff, 0xd7b4f3, 0xffb0d9, 0xc8c8c8],
+        button_text_color: 0x000000,
+        button_hover_color: 0xf5f5f5,
+        button_activated_color: 0xf0f2a4,
+        cell_text_weight_fixed_value: 700,
+        cell_text_weight_nonfixed_value: 550,
+        cell_radius: 5.0,
+        cell_spacing: 0.0,
+        cells_outer_border: 1.0,
+    },
+    BuiltinTheme {
+        name: "DARK",
+        window_background_color: 0x1e1e1e,
+        game_background_color: 0x252526,
+        game_border: 2.0,
+        game_border_color: 0xd0d0d0,
+        game_radius: 2.0,
+        game_text_color: 0x8a8a8a,
+        game_highlight_color: 0x4fa3e0,
+        cell_border: 1.0,
+        cell_border_color: 0x101010,
+        cell_background_color_white: 0x3a3a3d,
+        cell_background_color_black: 0x0b0b0b,
+        cell_background_color_solved: 0x2e6b2e,
+        cell_background_color_editing_white: 0x6a6a30,
+        cell_background_color_editing_black: 0x3a3a20,
+        cell_background_color_wrong: 0x8b2c2c,
+        cell_background_color_peer: 0x414449,
+        cell_background_color_compartment: 0x46505c,
+        cell_background_color_same_value: 0x2f4f6f,
+        cell_background_color_ambiguous: 0x7a5a2a,
+        cell_text_color_black: 0xe0e0e0,
+        cell_text_color_white: 0xe6e6e6,
+        cell_text_color_wrong: 0xff6b6b,
+        cell_text_color_highlighted_small_value: 0x6cb6ff,
+        marking_colors: [0x8a6d2e, 0x3f7a3a, 0x2f5f8f, 0x6a4a86, 0x8a3f66, 0x5a5a5a],
+        button_text_color: 0xe0e0e0,
+        button_hover_color: 0x3a3a3a,
+        button_activated_color: 0x5a5a2a,
+        cell_text_weight_fixed_value: 700,
+        cell_text_weight_nonfixed_value: 550,
+        cell_radius: 5.0,
+        cell_spacing: 0.0,
+        cells_outer_border: 1.0,
+    },
+    BuiltinTheme {
+        name: "HIGH CONTRAST",
+        window_background_color: 0xffffff,
+        game_background_color: 0xffffff,
+        game_border: 3.0,
+        game_border_color: 0x000000,
+        game_radius: 2.0,
+        game_text_color: 0x000000,
+        game_highlight_color: 0x0000ff,
+        cell_border: 2.0,
+        cell_border_color: 0x000000,
+        cell_background_color_white: 0xffffff,
+        cell_background_color_black: 0x000000,
+        cell_background_color_solved: 0x00ff00,
+        cell_background_color_editing_white: 0xffff00,
+        cell_background_color_editing_black: 0x404000,
+        cell_background_color_wrong: 0xff0000,
+        cell_background_color_peer: 0xe0e0e0,
+        cell_background_color_compartment: 0xc0c0ff,
+        cell_background_color_same_value: 0x00ffff,
+        cell_background_color_ambiguous: 0xff8000,
+        cell_text_color_black: 0xffffff,
+        cell_text_color_white: 0x000000,
+        cell_text_color_wrong: 0xb00000,
+        cell_text_color_highlighted_small_value: 0x0000ff,
+        marking_colors: [0xffff00, 0x00ff00, 0x00ffff, 0xff00ff, 0xff8000, 0xa0a0a0],
+        button_text_color: 0x000000,
+        button_hover_color: 0xe0e0e0,
+        button_activated_color: 0xffff00,
+        cell_text_weight_fixed_value: 900,
+        cell_text_weight_nonfixed_value: 600,
+        cell_radius: 5.0,
+        cell_spacing: 0.0,
+        cells_outer_border: 3.0,
+    },
+    BuiltinTheme {
+        name: "SEPIA",
+        window_background_color: 0xf4ecd8,
+        game_background_color: 0xf4ecd8,
+        game_border: 2.0,
+        game_border_color: 0x5b4636,
+        game_radius: 2.0,
+        game_text_color: 0x8b7355,
+        game_highlight_color: 0x8b4513,
+        cell_border: 1.0,
+        cell_border_color: 0x5b4636,
+        cell_background_color_white: 0xfbf5e6,
+        cell_background_color_black: 0x3e2f23,
+        cell_background_color_solved: 0xc9dba0,
+        cell_background_color_editing_white: 0xecd9a0,
+        cell_background_color_editing_black: 0x5a4630,
+        cell_background_color_wrong: 0xe08a6a,
+        cell_background_color_peer: 0xefe5cc,
+        cell_background_color_compartment: 0xe6d7b4,
+        cell_background_color_same_value: 0xd9c08c,
+        cell_background_color_ambiguous: 0xe8b878,
+        cell_text_color_black: 0xf4ecd8,
+        cell_text_color_white: 0x3e2f23,
+        cell_text_color_wrong: 0xa0301a,
+        cell_text_color_highlighted_small_value: 0x8b4513,
+        marking_colors: [0xe8c27a, 0xb5c98a, 0x9fb8c8, 0xc4a8c8, 0xe0a8a0, 0xbfb5a0],
+        button_text_color: 0x3e2f23,
+        button_hover_color: 0xefe5cc,
+        button_activated_color: 0xecd9a0,
+        cell_text_weight_fixed_value: 700,
+        cell_text_weight_nonfixed_value: 550,
+        cell_radius: 5.0,
+        cell_spacing: 0.0,
+        cells_outer_border: 1.0,
+    },
+];
+
+// A built-in theme by its name
+pub fn builtin_theme(name: &str) -> Option<&'static BuiltinTheme> {
+    BUILTIN_THEMES.iter().find(|theme| theme.name == name)
+}
+
+impl BuiltinTheme {
+    // Colors of pictures of boards in this theme
+    pub fn board_colors(&self) -> BoardColors {
+        BoardColors {
+            background: rgba(self.game_background_color),
+            border: rgba(self.game_border_color),
+            cell_border: rgba(self.cell_border_color),
+            cell_white: rgba(self.cell_background_color_white),
+            cell_black: rgba(self.cell_background_color_black),
+            text_white: rgba(self.cell_text_color_white),
+            text_black: rgba(self.cell_text_color_black),
+        }
+    }
+}
+
+// Split 0xrrggbb into its components
+pub fn rgb(color: u32) -> (u8, u8, u8) {
+    ((color >> 16) as u8, (color >> 8) as u8, color as u8)
+}
+
+fn rgba(color: u32) -> Rgba<u8> {
+    let (red, green, blue) = rgb(color);
+    Rgba([red, green, blue, 0xff])
+}
@@ -0,0 +1,143 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use super::board::Board;
+use super::glyphs::{glyph_pixel, GLYPH_HEIGHT, GLYPH_WIDTH};
+use super::rules::box_shape;
+use image::{Rgba, RgbaImage};
+
+// Digits smaller than this many pixels are left out, they would be unreadable
+const MIN_DIGIT_HEIGHT: u32 = 5;
+
+// Subpixels per pixel in each direction, for smooth digit edges
+const SUPERSAMPLING: u32 = 4;
+
+// Colors of a board picture, taken from a built-in theme (see BuiltinTheme::board_colors):
+// game-background-color, game-border-color, cell-border-color,
+// cell-background-color-white/-black and cell-text-color-white/-black.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BoardColors {
+    pub background: Rgba<u8>,
+    pub border: Rgba<u8>,
+    pub cell_border: Rgba<u8>,
+    pub cell_white: Rgba<u8>,
+    pub cell_black: Rgba<u8>,
+    pub text_white: Rgba<u8>, // digits in white cells
+    pub text_black: Rgba<u8>, // digits in black cells
+}
+
+// Draw a board into a square picture of the given size in pixels, without a display. Cells
+// show their color and digit; fixed digits are bold. Outside clues are left out.
+pub fn render_thumbnail(board: &Board, size: u32, colors: &BoardColors) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(size, size, colors.background);
+    let n = board.size() as u32;
+    // Border lines get thicker on large pictures
+    let line = (size / 200).max(1);
+    let border = 2 * line;
+    let area = size.saturating_sub(2 * border);
+    // Cell edges from 0 to n, in pixels
+    let edge = |k: u32| border + k * area / n.max(1);
+
+    let layout = board.layout();
+    for (i, cell) in board.cells().iter().enumerate() {
+        if !layout.is_used(i) {
+            continue;
+        }
+        // On screen, cell i is in row i % n and column i / n
+        let (row, column) = (i as u32 % n, i as u32 / n);
+        let (x0, y0, x1, y1) = (edge(column), edge(row), edge(column + 1), edge(row + 1));
+        let (background, text) = if cell.is_white() {
+            (colors.cell_white, colors.text_white)
+        } else {
+            (colors.cell_black, colors.text_black)
+        };
+        fill_rect(&mut image, (x0, y0, x1, y1), background);
+        stroke_rect(&mut image, (x0, y0, x1 + 1, y1 + 1), 1, colors.cell_border);
+        if let Some(digit) = cell.digit {
+            draw_glyph(&mut image, (x0, y0, x1, y1), text, cell.is_fixed, |x, y| glyph_pixel(digit, x, y));
+        }
+    }
+
+    // Boxes of Sudoku-Str8ts span rows × columns cells on screen
+    if let Some((rows, columns)) = box_shape(n as usize).filter(|_| layout.is_single() && board.rules().has_boxes(n as usize)) {
+        for row in (0..n).step_by(rows) {
+            for column in (0..n).step_by(columns) {
+                let rect = (edge(column), edge(row), edge(column + columns as u32) + 1, edge(row + rows as u32) + 1);
+                stroke_rect(&mut image, rect, line, colors.cell_border);
+            }
+        }
+    }
+    let grid_size = layout.grid_size as u32;
+    for grid in &layout.grids {
+        let (row, column) = (grid.row as u32, grid.column as u32);
+        let rect = (edge(column) - line, edge(row) - line, edge(column + grid_size) + line + 1, edge(row + grid_size) + line + 1);
+        stroke_rect(&mut image, rect, 2 * line, colors.border);
+    }
+    image
+}
+
+// Encode a picture as PNG
+pub fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut data = vec![];
+    image::png::PngEncoder::new(&mut data)
+        .encode(image.as_raw(), image.width(), image.height(), image::ColorType::Rgba8)
+        .map_err(|error| format!("unable to encode PNG: {}.", error))?;
+    Ok(data)
+}
+
+// Fill the pixels from (x0, y0) up to (x1, y1), without the end
+fn fill_rect(image: &mut RgbaImage, (x0, y0, x1, y1): (u32, u32, u32, u32), color: Rgba<u8>) {
+    for y in y0..y1.min(image.height()) {
+        for x in x0..x1.min(image.width()) {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+// Draw lines of the given width along the inside of a rectangle
+fn stroke_rect(image: &mut RgbaImage, (x0, y0, x1, y1): (u32, u32, u32, u32), width: u32, color: Rgba<u8>) {
+    fill_rect(image, (x0, y0, x1, y0 + width), color);
+    fill_rect(image, (x0, y1.saturating_sub(width), x1, y1), color);
+    fill_rect(image, (x0, y0, x0 + width, y1), color);
+    fill_rect(image, (x1.saturating_sub(width), y0, x1, y1), color);
+}
+
+// Draw a glyph centered in a cell, 0.6 of the cell high. Bold glyphs are widened by a glyph
+// pixel. Edge pixels are blended by how much of them the glyph covers.
+fn draw_glyph(image: &mut RgbaImage, (x0, y0, x1, y1): (u32, u32, u32, u32), color: Rgba<u8>, is_bold: bool,
+        glyph: impl Fn(usize, usize) -> bool) {
+    let height = (y1 - y0) * 3 / 5;
+    if height < MIN_DIGIT_HEIGHT {
+        return;
+    }
+    let scale = height as f64 / GLYPH_HEIGHT as f64;
+    let width = (GLYPH_WIDTH as f64 * scale).round() as u32;
+    let (left, top) = (x0 + (x1 - x0).saturating_sub(width) / 2, y0 + (y1 - y0 - height) / 2);
+    let is_set = |x: usize, y: usize| glyph(x, y) || (is_bold && x > 0 && glyph(x - 1, y));
+    for y in top..(top + height).min(image.height()) {
+        for x in left..(left + width).min(image.width()) {
+            let mut covered = 0;
+            for sample in 0..SUPERSAMPLING * SUPERSAMPLING {
+                let sx = (x - left) as f64 + ((sample % SUPERSAMPLING) as f64 + 0.5) / SUPERSAMPLING as f64;
+                let sy = (y - top) as f64 + ((sample / SUPERSAMPLING) as f64 + 0.5) / SUPERSAMPLING as f64;
+                let (gx, gy) = ((sx / scale) as usize, (sy / scale) as usize);
+                if gx < GLYPH_WIDTH && gy < GLYPH_HEIGHT && is_set(gx, gy) {
+                    covered += 1;
+                }
+            }
+            if covered > 0 {
+                let coverage = covered as f64 / (SUPERSAMPLING * SUPERSAMPLING) as f64;
+                let pixel = image.get_pixel_mut(x, y);
+                for k in 0..4 {
+                    pixel.0[k] = (pixel.0[k] as f64 * (1.0 - coverage) + color.0[k] as f64 * coverage).round() as u8;
+                }
+            }
+        }
+    }
+}
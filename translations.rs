@@ -0,0 +1,309 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use str8ts_core::constraints::{Explanation, Unit};
+
+// Texts of the UI in one language
+pub struct Translation {
+    pub code: &'static str,
+    pub name: &'static str, // name of the language in that language
+    // Buttons
+    pub edit: &'static str,
+    pub play: &'static str,
+    pub new: &'static str,
+    pub save: &'static str,
+    pub load: &'static str,
+    pub solve: &'static str,
+    pub generate: &'static str,
+    pub ok: &'static str,
+    pub cancel: &'static str,
+    pub close: &'static str,
+    pub dont_ask_again: &'static str,
+    pub start_playing: &'static str,
+    pub edit_puzzle: &'static str,
+    pub solver_steps: &'static str,
+    pub boxes: &'static str,
+    pub import_image: &'static str,
+    pub print: &'static str,
+    // Settings screen
+    pub theme: &'static str,
+    pub language: &'static str,
+    pub highlights: &'static str,
+    pub highlight_peers: &'static str,
+    pub highlight_compartment: &'static str,
+    pub highlight_same_value: &'static str,
+    pub gameplay: &'static str,
+    pub auto_remove_small_values: &'static str,
+    pub strict_check: &'static str,
+    pub generator: &'static str,
+    pub generator_black_cells: &'static str,
+    pub board_size: &'static str,
+    pub samurai: &'static str,
+    pub confirmations: &'static str,
+    pub confirm_restart: &'static str,
+    pub confirm_new_board: &'static str,
+    pub confirm_solve: &'static str,
+    pub confirm_generate: &'static str,
+    pub confirm_load: &'static str,
+    pub confirm_import_image: &'static str,
+    // Confirmation dialogs
+    pub dialog_restart: &'static str,
+    pub dialog_new_board: &'static str,
+    pub dialog_solve: &'static str,
+    pub dialog_generate: &'static str,
+    pub dialog_load: &'static str,
+    pub dialog_import_image: &'static str,
+    pub dialog_multiple_solutions: &'static str,
+    // Status messages
+    pub status_invalid: &'static str,
+    pub status_no_solution: &'static str,
+    pub status_cancelled: &'static str,
+    pub status_generation_failed: &'static str,
+    pub status_import_failed: &'static str,
+    pub status_load_failed: &'static str,
+    pub status_save_failed: &'static str,
+    pub status_imported: &'static str,
+    pub status_printed: &'static str,
+    pub status_print_failed: &'static str,
+    // Background jobs, {} is replaced by the progress
+    pub busy_solving: &'static str,
+    pub busy_generating: &'static str,
+    pub busy_recording: &'static str,
+    pub busy_checking: &'static str,
+    // Replay of solver steps, {cell} is replaced by the cell name
+    pub cell_name: &'static str,
+    pub replay_place: &'static str,
+    pub replay_eliminate: &'static str,
+    pub replay_backtrack: &'static str,
+    pub replay_finished: &'static str,
+    pub replay_truncated: &'static str,
+    pub replay_finished_truncated: &'static str,
+    // Explanations of broken rules
+    pub row: &'static str,
+    pub column: &'static str,
+    pub box_name: &'static str,
+    pub explain_duplicate: &'static str,
+    pub explain_straight: &'static str,
+    pub explain_clue_sum: &'static str,
+    pub explain_clue_min: &'static str,
+    pub explain_clue_max: &'static str,
+    // Puzzle analysis while editing, {} is replaced by a number or a difficulty
+    pub analysis_checking: &'static str,
+    pub analysis_unique: &'static str,
+    pub analysis_multiple: &'static str,
+    pub analysis_many: &'static str,
+    pub difficulty_easy: &'static str,
+    pub difficulty_medium: &'static str,
+    pub difficulty_hard: &'static str,
+    pub difficulty_extreme: &'static str,
+}
+
+pub static TRANSLATIONS: [Translation; 2] = [
+    Translation {
+        code: "en",
+        name: "English",
+        edit: "Edit:",
+        play: "Play:",
+        new: "New",
+        save: "Save",
+        load: "Load",
+        solve: "Solve",
+        generate: "Generate",
+        ok: "OK",
+        cancel: "Cancel",
+        close: "Close",
+        dont_ask_again: "Don't ask again",
+        start_playing: "Play ▶",
+        edit_puzzle: "✎",
+        solver_steps: "Steps",
+        boxes: "Boxes",
+        import_image: "Import",
+        print: "Print",
+        theme: "Theme:",
+        language: "Language:",
+        highlights: "Highlight for the focused cell:",
+        highlight_peers: "Row and column",
+        highlight_compartment: "Compartments",
+        highlight_same_value: "Same number",
+        gameplay: "Playing:",
+        auto_remove_small_values: "Entering a number removes it from small numbers in row and column",
+        strict_check: "Refuse numbers that break a rule",
+        generator: "Generator:",
+        generator_black_cells: "Black cells",
+        board_size: "Board size",
+        samurai: "Samurai (five overlapping 9×9 grids)",
+        confirmations: "Ask for confirmation before:",
+        confirm_restart: "Restarting the puzzle",
+        confirm_new_board: "Clearing the board",
+        confirm_solve: "Solving the puzzle",
+        confirm_generate: "Generating a puzzle",
+        confirm_load: "Loading the saved game",
+        confirm_import_image: "Importing an image",
+        dialog_restart: "Restart the puzzle? All entered numbers and markings are removed.",
+        dialog_new_board: "Clear the whole board, including black cells and given numbers?",
+        dialog_solve: "Solve the puzzle? All entered numbers are replaced by the solution.",
+        dialog_generate: "Generate a new puzzle? The current puzzle and your progress are lost.",
+        dialog_load: "Load the saved game? Your current progress is lost.",
+        dialog_import_image: "Replace the board by the puzzle in the image {}?",
+        dialog_multiple_solutions: "The puzzle has more than one solution. Play it anyway?",
+        status_invalid: "The puzzle breaks a rule.",
+        status_no_solution: "The puzzle has no solution.",
+        status_cancelled: "Cancelled.",
+        status_generation_failed: "No puzzle could be generated.",
+        status_import_failed: "The image could not be imported: {}",
+        status_load_failed: "The game could not be loaded: {}",
+        status_save_failed: "The game could not be saved: {}",
+        status_imported: "Puzzle imported. Please check the cells and numbers.",
+        status_printed: "Printable PDF written to {}.",
+        status_print_failed: "The PDF could not be written: {}",
+        busy_solving: "Solving… ({} steps)",
+        busy_generating: "Generating a puzzle… (step {})",
+        busy_recording: "Recording the solving steps… ({} steps)",
+        busy_checking: "Checking the puzzle… ({} steps)",
+        cell_name: "r{row}c{column}",
+        replay_place: "{cell}: place {value}",
+        replay_eliminate: "{cell}: eliminate {values}",
+        replay_backtrack: "{cell}: backtrack",
+        replay_finished: "All steps shown.",
+        replay_truncated: "{} (incomplete)",
+        replay_finished_truncated: "All recorded steps shown. The solver was stopped before it finished.",
+        row: "Row {}",
+        column: "Column {}",
+        box_name: "Box {}",
+        explain_duplicate: "{digit} appears {count} times",
+        explain_straight: "{digits} span {span} numbers ({min}–{max}), but the straight has only {length} cells",
+        explain_clue_sum: "the first straight adds up to {sum}, but the clue is {value}",
+        explain_clue_min: "the lowest number must be {value}",
+        explain_clue_max: "the highest number must be {value}",
+        analysis_checking: "Checking the puzzle…",
+        analysis_unique: "Unique solution. Estimated difficulty: {}",
+        analysis_multiple: "{} solutions. Cells that differ are marked.",
+        analysis_many: "{}+ solutions. Cells that differ are marked.",
+        difficulty_easy: "easy",
+        difficulty_medium: "medium",
+        difficulty_hard: "hard",
+        difficulty_extreme: "extreme",
+    },
+    Translation {
+        code: "de",
+        name: "Deutsch",
+        edit: "Bearbeiten:",
+        play: "Spielen:",
+        new: "Neu",
+        save: "Speichern",
+        load: "Laden",
+        solve: "Lösen",
+        generate: "Erzeugen",
+        ok: "OK",
+        cancel: "Abbrechen",
+        close: "Schließen",
+        dont_ask_again: "Nicht mehr fragen",
+        start_playing: "Spielen ▶",
+        edit_puzzle: "✎",
+        solver_steps: "Schritte",
+        boxes: "Kästen",
+        import_image: "Importieren",
+        print: "Drucken",
+        theme: "Farbschema:",
+        language: "Sprache:",
+        highlights: "Hervorheben für die ausgewählte Zelle:",
+        highlight_peers: "Zeile und Spalte",
+        highlight_compartment: "Straßen",
+        highlight_same_value: "Gleiche Zahl",
+        gameplay: "Spielen:",
+        auto_remove_small_values: "Eingegebene Zahl aus den kleinen Zahlen in Zeile und Spalte entfernen",
+        strict_check: "Zahlen ablehnen, die eine Regel verletzen",
+        generator: "Generator:",
+        generator_black_cells: "Schwarze Zellen",
+        board_size: "Spielfeldgröße",
+        samurai: "Samurai (fünf überlappende 9×9-Gitter)",
+        confirmations: "Nachfragen vor:",
+        confirm_restart: "Neustart des Rätsels",
+        confirm_new_board: "Leeren des Spielfelds",
+        confirm_solve: "Lösen des Rätsels",
+        confirm_generate: "Erzeugen eines Rätsels",
+        confirm_load: "Laden des gespeicherten Spiels",
+        confirm_import_image: "Importieren eines Bildes",
+        dialog_restart: "Rätsel neu starten? Alle eingegebenen Zahlen und Markierungen werden entfernt.",
+        dialog_new_board: "Ganzes Spielfeld leeren, auch schwarze Zellen und vorgegebene Zahlen?",
+        dialog_solve: "Rätsel lösen? Alle eingegebenen Zahlen werden durch die Lösung ersetzt.",
+        dialog_generate: "Neues Rätsel erzeugen? Das aktuelle Rätsel und dein Fortschritt gehen verloren.",
+        dialog_load: "Gespeichertes Spiel laden? Dein aktueller Fortschritt geht verloren.",
+        dialog_import_image: "Spielfeld durch das Rätsel im Bild {} ersetzen?",
+        dialog_multiple_solutions: "Das Rätsel hat mehr als eine Lösung. Trotzdem spielen?",
+        status_invalid: "Das Rätsel verletzt eine Regel.",
+        status_no_solution: "Das Rätsel hat keine Lösung.",
+        status_cancelled: "Abgebrochen.",
+        status_generation_failed: "Es konnte kein Rätsel erzeugt werden.",
+        status_import_failed: "Das Bild konnte nicht importiert werden: {}",
+        status_load_failed: "Das Spiel konnte nicht geladen werden: {}",
+        status_save_failed: "Das Spiel konnte nicht gespeichert werden: {}",
+        status_imported: "Rätsel importiert. Bitte Zellen und Zahlen prüfen.",
+        status_printed: "Druckbare PDF-Datei nach {} geschrieben.",
+        status_print_failed: "Die PDF-Datei konnte nicht geschrieben werden: {}",
+        busy_solving: "Löse… ({} Schritte)",
+        busy_generating: "Erzeuge ein Rätsel… (Schritt {})",
+        busy_recording: "Zeichne die Lösungsschritte auf… ({} Schritte)",
+        busy_checking: "Prüfe das Rätsel… ({} Schritte)",
+        cell_name: "Z{row}S{column}",
+        replay_place: "{cell}: {value} eintragen",
+        replay_eliminate: "{cell}: {values} streichen",
+        replay_backtrack: "{cell}: zurücknehmen",
+        replay_finished: "Alle Schritte gezeigt.",
+        replay_truncated: "{} (unvollständig)",
+        replay_finished_truncated: "Alle aufgezeichneten Schritte gezeigt. Der Löser wurde vorher angehalten.",
+        row: "Zeile {}",
+        column: "Spalte {}",
+        box_name: "Kasten {}",
+        explain_duplicate: "{digit} kommt {count}-mal vor",
+        explain_straight: "{digits} umfassen {span} Zahlen ({min}–{max}), die Straße hat aber nur {length} Zellen",
+        explain_clue_sum: "die erste Straße ergibt {sum}, der Hinweis ist aber {value}",
+        explain_clue_min: "die kleinste Zahl muss {value} sein",
+        explain_clue_max: "die größte Zahl muss {value} sein",
+        analysis_checking: "Rätsel wird geprüft…",
+        analysis_unique: "Eindeutige Lösung. Geschätzte Schwierigkeit: {}",
+        analysis_multiple: "{} Lösungen. Abweichende Zellen sind markiert.",
+        analysis_many: "{}+ Lösungen. Abweichende Zellen sind markiert.",
+        difficulty_easy: "leicht",
+        difficulty_medium: "mittel",
+        difficulty_hard: "schwer",
+        difficulty_extreme: "extrem",
+    },
+];
+
+impl Translation {
+    // Text of an explanation of a broken rule by its id (see str8ts_core::constraints)
+    pub fn explanation(&self, id: &str) -> &'static str {
+        match id {
+            "duplicate" => self.explain_duplicate,
+            "straight" => self.explain_straight,
+            "clue_sum" => self.explain_clue_sum,
+            "clue_min" => self.explain_clue_min,
+            "clue_max" => self.explain_clue_max,
+            _ => panic!("Unknown explanation {}.", id)
+        }
+    }
+}
+
+// Translation for a language code, English if the language is not available
+pub fn translation(code: &str) -> &'static Translation {
+    TRANSLATIONS.iter().find(|t| t.code == code).unwrap_or(&TRANSLATIONS[0])
+}
+
+// Describe a broken rule in the given language
+pub fn explain_violation(t: &Translation, explanation: &Explanation) -> String {
+    let unit = match explanation.unit {
+        Unit::Row(k) => t.row.replace("{}", &(k + 1).to_string()),
+        Unit::Column(k) => t.column.replace("{}", &(k + 1).to_string()),
+        Unit::Box(k) => t.box_name.replace("{}", &(k + 1).to_string()),
+    };
+    let text = explanation.args.iter().fold(t.explanation(explanation.text).to_string(),
+        |text, (name, value)| text.replace(&format!("{{{}}}", name), value));
+    format!("{}: {}", unit, text)
+}
@@ -0,0 +1,280 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use str8ts_core::themes::{rgb, BuiltinTheme, BUILTIN_THEMES};
+use crate::ui::Theme;
+use serde::Deserialize;
+use sixtyfps::{Brush, Color, ModelHandle, VecModel};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+// A length, either in px as a number or as a string like "2px"
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LengthValue {
+    Number(f32),
+    Text(String),
+}
+
+// Contents of a user theme file (JSON or TOML). Field names are the same as in the
+// Theme struct of the .60 file; missing fields are taken from the base theme.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct ThemeFile {
+    name: String,
+    base: Option<String>, // name of the built-in theme to start from
+    window_background_color: Option<String>,
+    game_background_color: Option<String>,
+    game_border: Option<LengthValue>,
+    game_border_color: Option<String>,
+    game_radius: Option<LengthValue>,
+    game_text_color: Option<String>,
+    game_highlight_color: Option<String>,
+    cell_border: Option<LengthValue>,
+    cell_border_color: Option<String>,
+    cell_background_color_white: Option<String>,
+    cell_background_color_black: Option<String>,
+    cell_background_color_solved: Option<String>,
+    cell_background_color_editing_white: Option<String>,
+    cell_background_color_editing_black: Option<String>,
+    cell_background_color_wrong: Option<String>,
+    cell_background_color_peer: Option<String>,
+    cell_background_color_compartment: Option<String>,
+    cell_background_color_same_value: Option<String>,
+    cell_background_color_ambiguous: Option<String>,
+    cell_text_color_black: Option<String>,
+    cell_text_color_white: Option<String>,
+    cell_text_color_wrong: Option<String>,
+    cell_text_color_highlighted_small_value: Option<String>,
+    marking_colors: Option<Vec<String>>,
+    button_text_color: Option<String>,
+    button_hover_color: Option<String>,
+    button_activated_color: Option<String>,
+    cell_text_weight_fixed_value: Option<i32>,
+    cell_text_weight_nonfixed_value: Option<i32>,
+    cell_text_font_family: Option<String>,
+    cell_radius: Option<LengthValue>,
+    cell_spacing: Option<LengthValue>,
+    cells_outer_border: Option<LengthValue>,
+}
+
+// Parse a color of the form #rgb, #rrggbb or #rrggbbaa
+fn parse_color(field: &str, text: &str) -> Result<Color, String> {
+    let invalid = || format!("{}: invalid color \"{}\", expected #rrggbb or #rrggbbaa", field, text);
+    let hex = text.strip_prefix('#').ok_or_else(invalid)?;
+    let hex = if hex.len() == 3 { hex.chars().flat_map(|c| vec![c, c]).collect() } else { hex.to_string() };
+    if (hex.len() != 6 && hex.len() != 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let component = |k: usize| u8::from_str_radix(&hex[2*k..2*k + 2], 16).unwrap();
+    let alpha = if hex.len() == 8 { component(3) } else { 255 };
+    Ok(Color::from_argb_u8(alpha, component(0), component(1), component(2)))
+}
+
+fn parse_brush(field: &str, text: &str) -> Result<Brush, String> {
+    parse_color(field, text).map(Brush::SolidColor)
+}
+
+// Parse a non-negative length in px, given as a number or as a string like "2px"
+fn parse_length(field: &str, value: &LengthValue) -> Result<f32, String> {
+    let length = match value {
+        LengthValue::Number(length) => *length,
+        LengthValue::Text(text) => text.trim().trim_end_matches("px").trim().parse::<f32>()
+            .map_err(|_| format!("{}: invalid length \"{}\", expected e.g. \"2px\"", field, text))?
+    };
+    if !length.is_finite() || !(0.0..=100.0).contains(&length) {
+        return Err(format!("{}: length {}px out of range 0px-100px", field, length));
+    }
+    Ok(length)
+}
+
+// Parse a ratio, given as a number or as a percentage string like "5%"
+fn parse_ratio(field: &str, value: &LengthValue) -> Result<f32, String> {
+    let ratio = match value {
+        LengthValue::Number(ratio) => *ratio,
+        LengthValue::Text(text) => text.trim().trim_end_matches('%').trim().parse::<f32>()
+            .map(|percent| percent / 100.0)
+            .map_err(|_| format!("{}: invalid ratio \"{}\", expected e.g. \"5%\"", field, text))?
+    };
+    if !ratio.is_finite() || !(0.0..=0.5).contains(&ratio) {
+        return Err(format!("{}: ratio {} out of range 0%-50%", field, ratio));
+    }
+    Ok(ratio)
+}
+
+fn parse_weight(field: &str, weight: &i32) -> Result<i32, String> {
+    if *weight < 100 || *weight > 900 {
+        return Err(format!("{}: font weight {} out of range 100-900", field, weight));
+    }
+    Ok(*weight)
+}
+
+fn parse_string(_field: &str, text: &str) -> Result<sixtyfps::SharedString, String> {
+    Ok(text.into())
+}
+
+fn parse_colors(field: &str, texts: &[String]) -> Result<ModelHandle<Color>, String> {
+    if texts.is_empty() {
+        return Err(format!("{}: at least one color is required", field));
+    }
+    let colors = texts.iter().map(|text| parse_color(field, text)).collect::<Result<Vec<_>, _>>()?;
+    Ok(ModelHandle::new(Rc::new(VecModel::from(colors))))
+}
+
+impl ThemeFile {
+    // Build a theme: start from the base theme and override all fields given in the file
+    fn to_theme(&self, builtin_themes: &[Theme]) -> Result<Theme, String> {
+        let mut theme = match self.base {
+            Some(ref base) => builtin_themes.iter().find(|theme| theme.name == base.as_str())
+                .ok_or_else(|| format!("base: unknown theme \"{}\"", base))?.clone(),
+            None => builtin_themes[0].clone()
+        };
+        theme.name = self.name.as_str().into();
+
+        macro_rules! apply {
+            ($parse:ident: $($field:ident),+) => { $(
+                if let Some(ref value) = self.$field {
+                    theme.$field = $parse(&stringify!($field).replace('_', "-"), value)?;
+                }
+            )+ }
+        }
+        apply!(parse_brush: window_background_color, game_background_color);
+        apply!(parse_color: game_border_color, game_text_color, game_highlight_color, cell_border_color,
+            cell_background_color_white, cell_background_color_black, cell_background_color_solved,
+            cell_background_color_editing_white, cell_background_color_editing_black, cell_background_color_wrong,
+            cell_background_color_peer, cell_background_color_compartment, cell_background_color_same_value,
+            cell_background_color_ambiguous, cell_text_color_black, cell_text_color_white, cell_text_color_wrong,
+            cell_text_color_highlighted_small_value, button_text_color, button_hover_color, button_activated_color);
+        apply!(parse_colors: marking_colors);
+        apply!(parse_length: game_border, game_radius, cell_border, cell_radius, cells_outer_border);
+        apply!(parse_ratio: cell_spacing);
+        apply!(parse_weight: cell_text_weight_fixed_value, cell_text_weight_nonfixed_value);
+        apply!(parse_string: cell_text_font_family);
+        Ok(theme)
+    }
+}
+
+// The built-in themes in the order of the theme picker
+pub fn builtin_themes() -> Vec<Theme> {
+    BUILTIN_THEMES.iter().map(theme_of_builtin).collect()
+}
+
+fn theme_of_builtin(theme: &BuiltinTheme) -> Theme {
+    let color = |c: u32| {
+        let (red, green, blue) = rgb(c);
+        Color::from_rgb_u8(red, green, blue)
+    };
+    Theme {
+        name: theme.name.into(),
+        window_background_color: Brush::SolidColor(color(theme.window_background_color)),
+        game_background_color: Brush::SolidColor(color(theme.game_background_color)),
+        game_border: theme.game_border,
+        game_border_color: color(theme.game_border_color),
+        game_radius: theme.game_radius,
+        game_text_color: color(theme.game_text_color),
+        game_highlight_color: color(theme.game_highlight_color),
+        cell_border: theme.cell_border,
+        cell_border_color: color(theme.cell_border_color),
+        cell_background_color_white: color(theme.cell_background_color_white),
+        cell_background_color_black: color(theme.cell_background_color_black),
+        cell_background_color_solved: color(theme.cell_background_color_solved),
+        cell_background_color_editing_white: color(theme.cell_background_color_editing_white),
+        cell_background_color_editing_black: color(theme.cell_background_color_editing_black),
+        cell_background_color_wrong: color(theme.cell_background_color_wrong),
+        cell_background_color_peer: color(theme.cell_background_color_peer),
+        cell_background_color_compartment: color(theme.cell_background_color_compartment),
+        cell_background_color_same_value: color(theme.cell_background_color_same_value),
+        cell_background_color_ambiguous: color(theme.cell_background_color_ambiguous),
+        cell_text_color_black: color(theme.cell_text_color_black),
+        cell_text_color_white: color(theme.cell_text_color_white),
+        cell_text_color_wrong: color(theme.cell_text_color_wrong),
+        cell_text_color_highlighted_small_value: color(theme.cell_text_color_highlighted_small_value),
+        marking_colors: ModelHandle::new(Rc::new(VecModel::from(
+            theme.marking_colors.iter().map(|&c| color(c)).collect::<Vec<_>>()))),
+        button_text_color: color(theme.button_text_color),
+        button_hover_color: color(theme.button_hover_color),
+        button_activated_color: color(theme.button_activated_color),
+        cell_text_weight_fixed_value: theme.cell_text_weight_fixed_value,
+        cell_text_weight_nonfixed_value: theme.cell_text_weight_nonfixed_value,
+        cell_text_font_family: "".into(), // the default font
+        cell_radius: theme.cell_radius,
+        cell_spacing: theme.cell_spacing,
+        cells_outer_border: theme.cells_outer_border,
+    }
+}
+
+// Load a theme from a .json or .toml file
+fn load_theme(path: &Path, builtin_themes: &[Theme]) -> Result<Theme, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let theme_file: ThemeFile = match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&text).map_err(|e| e.to_string())?,
+        Some("toml") => toml::from_str(&text).map_err(|e| e.to_string())?,
+        _ => return Err("unknown file type".into())
+    };
+    if builtin_themes.iter().any(|theme| theme.name == theme_file.name.as_str()) {
+        return Err(format!("name: \"{}\" is the name of a built-in theme", theme_file.name));
+    }
+    theme_file.to_theme(builtin_themes)
+}
+
+// Theme files (.json/.toml) in a directory, sorted by path
+fn theme_files(dir: &Path) -> Vec<PathBuf> {
+    let mut paths = std::fs::read_dir(dir).into_iter().flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| matches!(path.extension().and_then(|e| e.to_str()), Some("json") | Some("toml")))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+// Load all valid user themes from a directory. Invalid theme files are reported and skipped.
+pub fn load_user_themes(dir: &Path, builtin_themes: &[Theme]) -> Vec<Theme> {
+    let mut themes: Vec<Theme> = vec![];
+    for path in theme_files(dir) {
+        match load_theme(&path, builtin_themes) {
+            Ok(theme) if themes.iter().any(|t| t.name == theme.name) =>
+                println!("Skipping theme file {}: duplicate theme name \"{}\".", path.display(), theme.name),
+            Ok(theme) => themes.push(theme),
+            Err(e) => println!("Skipping invalid theme file {}: {}", path.display(), e)
+        }
+    }
+    themes
+}
+
+// Detects changes of the theme files in a directory by polling their modification times
+pub struct ThemeWatcher {
+    dir: PathBuf,
+    modification_times: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl ThemeWatcher {
+    pub fn new(dir: PathBuf) -> ThemeWatcher {
+        let mut watcher = ThemeWatcher { dir, modification_times: vec![] };
+        watcher.has_changed();
+        watcher
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Check if theme files were added, removed or modified since the last call
+    pub fn has_changed(&mut self) -> bool {
+        let modification_times = theme_files(&self.dir).into_iter()
+            .map(|path| {
+                let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                (path, modified)
+            })
+            .collect::<Vec<_>>();
+        let has_changed = modification_times != self.modification_times;
+        self.modification_times = modification_times;
+        has_changed
+    }
+}
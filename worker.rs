@@ -0,0 +1,81 @@
+/* LICENSE BEGIN
+    This file is part of SixtyFPS-Str8ts, a demo implementing the
+    Str8ts puzzle in the SixtyFPS framework. Based on the SixtyFPS
+    Slide Puzzle demo.
+    Copyright (c) 2021 Vincent Bürgin <v.buergin@gmx.de>
+
+    SPDX-License-Identifier: GPL-3.0-only
+LICENSE END */
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+
+// Lets a background job check whether it should stop early, and report its progress
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    is_cancelled: Arc<AtomicBool>,
+    progress: Arc<AtomicUsize>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.is_cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.is_cancelled.load(Ordering::Relaxed)
+    }
+
+    // Progress in a unit chosen by the job, e.g. steps of a search
+    pub fn set_progress(&self, progress: usize) {
+        self.progress.store(progress, Ordering::Relaxed);
+    }
+
+    pub fn progress(&self) -> usize {
+        self.progress.load(Ordering::Relaxed)
+    }
+}
+
+// A job running on a background thread. The UI thread picks up the result
+// by polling (from a timer). Dropping the job cancels it.
+pub struct Job<T> {
+    receiver: Receiver<T>,
+    token: CancellationToken,
+}
+
+impl<T: Send + 'static> Job<T> {
+    pub fn spawn<F>(f: F) -> Job<T>
+            where F: FnOnce(&CancellationToken) -> T + Send + 'static {
+        let (sender, receiver) = channel();
+        let token = CancellationToken::default();
+        let job_token = token.clone();
+        let run = move || {
+            // The receiver may be gone if the job was dropped meanwhile
+            let _ = sender.send(f(&job_token));
+        };
+
+        // There are no threads in the browser, run the job right away there
+        #[cfg(target_arch = "wasm32")]
+        run();
+        #[cfg(not(target_arch = "wasm32"))]
+        std::thread::spawn(run);
+
+        Job { receiver, token }
+    }
+
+    // Returns the result once the job has finished
+    pub fn poll(&self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+
+    pub fn progress(&self) -> usize {
+        self.token.progress()
+    }
+}
+
+impl<T> Drop for Job<T> {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}